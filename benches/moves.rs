@@ -0,0 +1,128 @@
+/*!
+* Move benchmarks, std only: `cargo bench --bench moves`
+* - Each benchmark is warmed up, then timed over SAMPLES samples of a fixed number of iterations.
+*   The time per iteration is shown as [fastest median slowest] over the samples, as criterion
+*   does, and each table based version is compared to the index math of `Cube::rotate_strips`.
+* - `cargo bench --bench moves -- <filter>` only runs the benchmarks whose name contains it.
+*/
+
+use rubik::cube::{Cube, Face, RotationDirection};
+use rubik::cubie::CubieCube;
+use rubik::movetable::{coord_tables, cubie_moves, sticker_moves};
+use rubik::notation::{face_moves, Layer, Move};
+use rubik::train::Rng;
+use std::env;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const SAMPLES: usize = 50;
+const WARM_UP: Duration = Duration::from_millis(300);
+const SAMPLE_TIME: Duration = Duration::from_millis(40);
+const SEQUENCE_LENGTH: usize = 1000;
+
+// "12.34ns", "1.23µs" or "1.23ms"
+fn format_ns(ns: f64) -> String {
+    match ns {
+        ns if ns < 1e3 => format!("{:.2}ns", ns),
+        ns if ns < 1e6 => format!("{:.2}µs", ns / 1e3),
+        ns => format!("{:.2}ms", ns / 1e6),
+    }
+}
+
+// Median time of one iteration in nanoseconds, after printing [fastest median slowest]
+fn bench(name: &str, mut iteration: impl FnMut()) -> Option<f64> {
+    if env::args().skip(1).filter(|arg| !arg.starts_with('-')).any(|filter| !name.contains(&filter)) {
+        return None;
+    }
+    // Warm up, and find how many iterations fill a sample
+    let start = Instant::now();
+    let mut count = 0u64;
+    while start.elapsed() < WARM_UP {
+        iteration();
+        count += 1;
+    }
+    let per_sample = (count as f64 * SAMPLE_TIME.as_secs_f64() / start.elapsed().as_secs_f64()).max(1.0) as u64;
+
+    // Not a Duration: dividing one truncates to whole nanoseconds
+    let mut times: Vec<f64> = (0..SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..per_sample {
+                iteration();
+            }
+            start.elapsed().as_secs_f64() * 1e9 / per_sample as f64
+        })
+        .collect();
+    times.sort_by(f64::total_cmp);
+    let median = times[SAMPLES / 2];
+    println!(
+        "{:<40} time: [{:>10} {:>10} {:>10}]",
+        name, format_ns(times[0]), format_ns(median), format_ns(times[SAMPLES - 1])
+    );
+    Some(median)
+}
+
+fn compare(slow: Option<f64>, fast: Option<f64>) {
+    if let (Some(slow), Some(fast)) = (slow, fast) {
+        println!("{:<40} speedup: {:.2}x\n", "", slow / fast);
+    }
+}
+
+fn main() {
+    let mut rng = Rng::new(1);
+    let moves = face_moves();
+    let sequence: Vec<Move> = (0..SEQUENCE_LENGTH).map(|_| moves[rng.below(moves.len())]).collect();
+    let face = |m: &Move| match m.layer {
+        Layer::Face(face) => face,
+        Layer::Slice(_) => unreachable!("Face moves only"),
+    };
+    let solved = Cube::new(None).expect("Error initializing cube");
+
+    // One turn: 1 to 3 quarter turns of index math, or one gather
+    for direction in [RotationDirection::Clockwise, RotationDirection::DoubleTurn, RotationDirection::CounterClockwise] {
+        let name = Move::face(Face::R, direction).to_string();
+        let mut cube = solved;
+        let strips = bench(&format!("{} index math", name), || cube.rotate_strips(black_box(Face::R), black_box(direction)));
+        let mut cube = solved;
+        let table = &sticker_moves()[Move::face(Face::R, direction).index()];
+        let gather = bench(&format!("{} sticker gather", name), || cube.gather(black_box(table)));
+        compare(strips, gather);
+    }
+
+    let mut cube = solved;
+    let strips = bench("1000 face moves index math", || {
+        for m in &sequence {
+            cube.rotate_strips(face(m), m.direction);
+        }
+    });
+    let mut cube = solved;
+    let tables = sticker_moves();
+    let gather = bench("1000 face moves sticker gather", || {
+        for m in &sequence {
+            cube.gather(&tables[m.index()]);
+        }
+    });
+    compare(strips, gather);
+
+    let mut cubie = CubieCube::solved();
+    let cubie_table = cubie_moves();
+    let multiply = bench("1000 face moves cubie multiply", || {
+        for m in &sequence {
+            cubie = cubie.multiply(&cubie_table[m.index()]);
+        }
+    });
+    compare(strips, multiply);
+
+    let coords = coord_tables();
+    let (mut twist, mut flip, mut corners) = (0u16, 0u16, 0u16);
+    let coordinates = bench("1000 face moves 3 coordinates", || {
+        for m in &sequence {
+            let m = m.index();
+            twist = coords.twist[twist as usize][m];
+            flip = coords.flip[flip as usize][m];
+            corners = coords.corner_perm[corners as usize][m];
+        }
+    });
+    compare(strips, coordinates);
+    black_box((cube, cubie, twist, flip, corners));
+}
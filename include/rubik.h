@@ -0,0 +1,88 @@
+/*
+ * C API of the rubik solver.
+ *
+ * Build the library with `cargo build --release`, then link target/release/librubik.a
+ * (static) or librubik.so / librubik.dylib / rubik.dll (shared). The static library also
+ * needs the system libraries Rust uses (e.g. -lpthread -ldl -lm on Linux).
+ *
+ * - Cubes are opaque, made by rubik_cube_new or rubik_cube_from_facelets and released by
+ *   rubik_cube_free.
+ * - Functions that can fail return a RubikStatus. When `error` isn't NULL, it receives a
+ *   message for any status but RUBIK_OK, to release with rubik_string_free.
+ * - Every string returned by the library is released by rubik_string_free, except the
+ *   static ones of rubik_status_message.
+ * - Strings are NUL terminated UTF-8. Moves use the usual notation with wide and slice moves
+ *   ("R U R' U2 M' r"), cube rotations (x, y, z) aren't moves and give RUBIK_INVALID_MOVES.
+ * - Facelets are 54 letters, the faces in the order U, D, R, L, F, B, each letter being the
+ *   face whose center has that color; '?' is an unknown sticker.
+ * - No Rust panic crosses the API: it gives RUBIK_PANIC (or NULL) instead.
+ * - A cube may be used by one thread at a time, different cubes from any threads.
+ */
+
+#ifndef RUBIK_H
+#define RUBIK_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct RubikCube RubikCube;
+
+typedef enum RubikStatus {
+    RUBIK_OK = 0,
+    RUBIK_NULL_ARGUMENT = 1,
+    RUBIK_INVALID_UTF8 = 2,
+    RUBIK_INVALID_FACELETS = 3,
+    RUBIK_INVALID_MOVES = 4,
+    RUBIK_INVALID_METHOD = 5,
+    RUBIK_UNSOLVABLE = 6,
+    RUBIK_NO_SOLUTION = 7,
+    RUBIK_TIMEOUT = 8,
+    RUBIK_PANIC = 9
+} RubikStatus;
+
+/* A solved cube, NULL only if the library fails. */
+RubikCube *rubik_cube_new(void);
+
+/* The cube with the given 54 facelets, in *cube. */
+RubikStatus rubik_cube_from_facelets(const char *facelets, RubikCube **cube, char **error);
+
+/* Release a cube, NULL is ignored. */
+void rubik_cube_free(RubikCube *cube);
+
+/* Turn the cube by the moves. The cube doesn't change when they can't be parsed. */
+RubikStatus rubik_cube_apply(RubikCube *cube, const char *moves, char **error);
+
+/* RUBIK_OK when moves can solve the cube (pieces, twists, flips and parity are possible),
+   RUBIK_UNSOLVABLE otherwise. */
+RubikStatus rubik_cube_validate(const RubikCube *cube, char **error);
+
+/* 1 when the cube is solved, 0 when it isn't, -1 for a NULL cube. */
+int rubik_cube_is_solved(const RubikCube *cube);
+
+/* The 54 facelets of the cube, NULL for a NULL cube. */
+char *rubik_cube_facelets(const RubikCube *cube);
+
+/*
+ * Solve the cube within time_limit_ms, the moves go to *solution.
+ * - method: "roux", "zz" or "optimal", roux when NULL.
+ * - metric: "htm", "qtm" or "stm" for the optimal search, htm when NULL.
+ * Every method stops at the time limit and solves on the calling thread. A time_limit_ms of 0
+ * always gives RUBIK_TIMEOUT, even for a solved cube.
+ */
+RubikStatus rubik_solve(const RubikCube *cube, const char *method, const char *metric,
+                        uint32_t time_limit_ms, char **solution, char **error);
+
+/* Release a string from the library, NULL is ignored. */
+void rubik_string_free(char *text);
+
+/* A static description of a status, not to be released. */
+const char *rubik_status_message(int status);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
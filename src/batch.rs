@@ -0,0 +1,232 @@
+/*!
+* Batch solving of many cubes, e.g. to generate datasets
+* - Input: one cube per line, as 54 facelets (see `Cube::from_facelets`) or a scramble, or as a
+*   JSON object `{"id"?, "facelets" | "scramble"}` (JSONL). Empty lines and # comments are
+*   skipped.
+* - A fixed number of workers solve the lines in parallel. They share the tables of the method,
+*   built once before they start, so no line pays for them.
+* - Results come out in input order, as JSONL or CSV: line, id, input, solution, length, nodes
+*   and time, or the error of the line. A failing line never stops the run.
+*/
+
+use crate::cube::Cube;
+use crate::json::Json;
+use crate::notation::{apply_moves, format_moves, parse_moves, Metric};
+use crate::solver::{preload, solve_timed, SolveMethod, Solved};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatchFormat {
+    Jsonl,
+    Csv,
+}
+
+#[derive(Clone, Debug)]
+pub struct BatchConfig {
+    pub method: SolveMethod,
+    pub time_limit: Duration, // For each line
+    pub jobs: usize,
+    pub format: BatchFormat,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        BatchConfig {
+            method: SolveMethod::Optimal(Metric::Htm),
+            time_limit: Duration::from_secs(10),
+            jobs: thread::available_parallelism().map_or(4, |n| n.get()),
+            format: BatchFormat::Jsonl,
+        }
+    }
+}
+
+// One line of the input and its cube
+pub struct BatchItem {
+    pub line: usize, // From 1
+    pub id: Option<String>,
+    pub input: String,
+    pub cube: Result<Cube, String>,
+}
+
+pub struct BatchResult {
+    pub item: BatchItem,
+    pub solved: Result<Solved, String>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BatchSummary {
+    pub lines: usize,
+    pub solved: usize,
+    pub time: Duration,
+}
+
+// Facelets when the text is one 54 letter word, a scramble otherwise
+fn text_cube(text: &str) -> Result<Cube, String> {
+    let text = text.trim();
+    if text.len() == Cube::CELL_COUNT && !text.contains(char::is_whitespace) {
+        return Cube::from_facelets(text);
+    }
+    let mut cube = Cube::new(None)?;
+    apply_moves(&mut cube, &parse_moves(text)?);
+    Ok(cube)
+}
+
+// The cube of a JSONL object, with its id
+fn json_cube(text: &str) -> (Option<String>, Result<Cube, String>) {
+    let request = match Json::parse(text) {
+        Ok(request @ Json::Object(_)) => request,
+        Ok(_) => return (None, Err("The line must be a JSON object".to_string())),
+        Err(e) => return (None, Err(e)),
+    };
+    let id = match request.get("id") {
+        None | Some(Json::Null) => None,
+        Some(Json::String(id)) => Some(id.clone()),
+        Some(id) => Some(id.to_string()),
+    };
+    let field = |key: &str| match request.get(key) {
+        None | Some(Json::Null) => Ok(None),
+        Some(value) => value.as_str().map(Some).ok_or_else(|| format!("{} must be a string", key)),
+    };
+    let cube = match (field("facelets"), field("scramble")) {
+        (Err(e), _) | (_, Err(e)) => Err(e),
+        (Ok(Some(facelets)), _) => Cube::from_facelets(facelets.trim()),
+        (Ok(None), Ok(Some(scramble))) => text_cube(scramble),
+        (Ok(None), Ok(None)) => Err("facelets or scramble is required".to_string()),
+    };
+    (id, cube)
+}
+
+// None for the lines to skip
+pub fn parse_line(line: usize, text: &str) -> Option<BatchItem> {
+    let input = text.trim();
+    if input.is_empty() || input.starts_with('#') {
+        return None;
+    }
+    let (id, cube) = if input.starts_with('{') { json_cube(input) } else { (None, text_cube(input)) };
+    Some(BatchItem { line, id, input: input.to_string(), cube })
+}
+
+fn solve_item(item: BatchItem, config: &BatchConfig) -> BatchResult {
+    let solved = match &item.cube {
+        Ok(cube) => solve_timed(cube, config.method, config.time_limit).map_err(|e| e.to_string()),
+        Err(e) => Err(e.clone()),
+    };
+    BatchResult { item, solved }
+}
+
+fn milliseconds(time: Duration) -> f64 {
+    (time.as_secs_f64() * 1e6).round() / 1e3
+}
+
+const CSV_HEADER: &str = "line,id,input,solution,length,nodes,time_ms,error";
+
+// Quoted when it holds a separator, a quote or a line break
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+impl BatchResult {
+    pub fn to_json(&self) -> Json {
+        let mut entries = vec![("line".to_string(), Json::from(self.item.line as f64))];
+        if let Some(id) = &self.item.id {
+            entries.push(("id".to_string(), Json::from(id.as_str())));
+        }
+        entries.push(("input".to_string(), Json::from(self.item.input.as_str())));
+        match &self.solved {
+            Ok(solved) => {
+                entries.push(("solution".to_string(), Json::from(format_moves(&solved.moves))));
+                entries.push(("length".to_string(), Json::from(solved.moves.len() as f64)));
+                if let Some(nodes) = solved.nodes {
+                    entries.push(("nodes".to_string(), Json::from(nodes as f64)));
+                }
+                entries.push(("time_ms".to_string(), Json::from(milliseconds(solved.time))));
+            }
+            Err(e) => entries.push(("error".to_string(), Json::from(e.as_str()))),
+        }
+        Json::Object(entries)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut fields = vec![self.item.line.to_string(), csv_field(self.item.id.as_deref().unwrap_or("")), csv_field(&self.item.input)];
+        match &self.solved {
+            Ok(solved) => fields.extend([
+                format_moves(&solved.moves),
+                solved.moves.len().to_string(),
+                solved.nodes.map_or(String::new(), |nodes| nodes.to_string()),
+                milliseconds(solved.time).to_string(),
+                String::new(),
+            ]),
+            Err(e) => fields.extend([String::new(), String::new(), String::new(), String::new(), csv_field(e)]),
+        }
+        fields.join(",")
+    }
+}
+
+/**
+Solve every line of `input` and write the results to `output`, in input order.
+- Returns an error only when the input can't be read or the output can't be written.
+*/
+pub fn run_batch(input: impl BufRead, output: &mut impl Write, config: &BatchConfig) -> Result<BatchSummary, String> {
+    let start = Instant::now();
+    let mut items = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line.map_err(|e| format!("Can't read line {}: {}", i + 1, e))?;
+        items.extend(parse_line(i + 1, &line));
+    }
+    let write_error = |e: std::io::Error| format!("Can't write the results: {}", e);
+    if config.format == BatchFormat::Csv {
+        writeln!(output, "{}", CSV_HEADER).map_err(write_error)?;
+    }
+
+    preload(config.method);
+    let count = items.len();
+    let items = Mutex::new(items.into_iter().enumerate());
+    let (sender, receiver) = mpsc::channel();
+    let mut summary = BatchSummary { lines: count, ..BatchSummary::default() };
+
+    thread::scope(|scope| -> Result<(), String> {
+        for _ in 0..config.jobs.clamp(1, count.max(1)) {
+            let sender = sender.clone();
+            let items = &items;
+            scope.spawn(move || {
+                // The lock is only held while taking a line
+                while let Some((index, item)) = items.lock().ok().and_then(|mut items| items.next()) {
+                    if sender.send((index, solve_item(item, config))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // Results arrive in any order, each one waits for those before it
+        let mut waiting = BTreeMap::new();
+        let mut written = 0;
+        for (index, result) in receiver {
+            waiting.insert(index, result);
+            while let Some(result) = waiting.remove(&written) {
+                if result.solved.is_ok() {
+                    summary.solved += 1;
+                }
+                let line = match config.format {
+                    BatchFormat::Jsonl => result.to_json().to_string(),
+                    BatchFormat::Csv => result.to_csv(),
+                };
+                writeln!(output, "{}", line).map_err(write_error)?;
+                written += 1;
+            }
+        }
+        Ok(())
+    })?;
+    output.flush().map_err(write_error)?;
+    summary.time = start.elapsed();
+    Ok(summary)
+}
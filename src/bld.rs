@@ -0,0 +1,476 @@
+/*!
+* Blindfolded solving: memo with Speffz letters and execution
+* - Every sticker of a corner or edge gets a Speffz letter (A-X), faces in order U, L, F, R, B, D,
+*   4 letters per face clockwise from the top left sticker.
+* - The memo follows the piece in the buffer to its home, letter by letter (cycle breaks go to the
+*   first unsolved piece), edges then corners, both read from the scrambled cube.
+* - Each letter is executed with a swap algorithm (Old Pochmann: T / Y perm, M2/R2: M2 / R2)
+*   between setup moves, or a special algorithm for the pieces the swap algorithm also moves.
+* - An odd number of edge letters (parity) leaves the side effects of the last swaps, undone by a
+*   parity algorithm: between edges and corners with Old Pochmann, at the end with M2/R2.
+*/
+
+use crate::cube::Cube;
+use crate::notation::{all_moves, format_moves, parse_moves, simplify_moves, Move};
+use crate::stage::{alg_perm, compose, for_each_sequence, identity, identity_perm, invert, move_perms, Perm};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+// Sticker of each Speffz letter A..X
+const EDGE_LETTERS: [usize; 24] = [
+    1, 5, 7, 3,     // U: UB, UR, UF, UL
+    28, 32, 34, 30, // L: LU, LF, LD, LB
+    37, 41, 43, 39, // F: FU, FR, FD, FL
+    19, 23, 25, 21, // R: RU, RB, RD, RF
+    46, 50, 52, 48, // B: BU, BL, BD, BR
+    10, 14, 16, 12, // D: DF, DR, DB, DL
+];
+const CORNER_LETTERS: [usize; 24] = [
+    0, 2, 8, 6,     // U: UBL, UBR, UFR, UFL
+    27, 29, 35, 33, // L: LUB, LUF, LDF, LDB
+    36, 38, 44, 42, // F: FUL, FUR, FDR, FDL
+    18, 20, 26, 24, // R: RUF, RUB, RDB, RDF
+    45, 47, 53, 51, // B: BUR, BUL, BDL, BDR
+    9, 11, 17, 15,  // D: DFL, DFR, DBR, DBL
+];
+
+const SETUP_MAX_DEPTH: usize = 4;
+const MAX_TARGETS: usize = 40;
+const SPECIAL_MAX_DEPTH: usize = 4;
+
+const T_PERM: &str = "R U R' U' R' F R2 U' R' U' R U R' F'";
+const Y_PERM: &str = "R U' R' U' R U R' F' R U R' U' R' F R"; // Old Pochmann version of the Y perm
+const A_PERM: &str = "R' F R' B2 R F' R' B2 R2";
+const CORNER_TWIST: &str = "R' D' R D R' D' R D U D' R' D R D' R' D R U'";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Buffers {
+    UfrUf, // Corner buffer UFR, edge buffer UF
+    UblUf, // Corner buffer UBL, edge buffer UF
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Execution {
+    OldPochmann, // Edges with a T perm, corners with a Y perm
+    M2R2,        // Edges with M2, corners with R2 (needs the UFR corner buffer)
+}
+
+#[derive(Clone, Debug)]
+pub struct Memo {
+    pub edges: Vec<char>,
+    pub corners: Vec<char>,
+    pub flipped_edges: Vec<&'static str>,    // Edges in their slot but flipped
+    pub twisted_corners: Vec<&'static str>,  // Corners in their slot but twisted
+    pub parity: bool,                        // Odd number of edge (and corner) targets
+}
+
+// Letters grouped by pairs: "AB CD E"
+fn letter_pairs(letters: &[char]) -> String {
+    letters.chunks(2).map(|pair| pair.iter().collect::<String>()).collect::<Vec<_>>().join(" ")
+}
+
+impl fmt::Display for Memo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Edges:   {}", letter_pairs(&self.edges))?;
+        writeln!(f, "Corners: {}", letter_pairs(&self.corners))?;
+        if !self.flipped_edges.is_empty() {
+            writeln!(f, "Flipped edges: {}", self.flipped_edges.join(" "))?;
+        }
+        if !self.twisted_corners.is_empty() {
+            writeln!(f, "Twisted corners: {}", self.twisted_corners.join(" "))?;
+        }
+        write!(f, "Parity: {}", if self.parity { "yes" } else { "no" })
+    }
+}
+
+// Execution of one letter
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub letter: char,
+    pub moves: Vec<Move>,
+}
+
+#[derive(Clone, Debug)]
+pub struct BldSolution {
+    pub memo: Memo,
+    pub edges: Vec<Step>,
+    pub corners: Vec<Step>,
+    pub parity: Vec<Move>, // Parity algorithm, empty without parity
+    pub execution: Execution,
+}
+
+impl BldSolution {
+    // Old Pochmann does the parity algorithm before the corners, M2/R2 after them
+    fn parity_first(&self) -> bool {
+        self.execution == Execution::OldPochmann
+    }
+
+    pub fn moves(&self) -> Vec<Move> {
+        let steps = |steps: &[Step]| steps.iter().flat_map(|step| step.moves.clone()).collect::<Vec<_>>();
+        let (edges, corners) = (steps(&self.edges), steps(&self.corners));
+        if self.parity_first() {
+            [edges, self.parity.clone(), corners].concat()
+        } else {
+            [edges, corners, self.parity.clone()].concat()
+        }
+    }
+}
+
+impl fmt::Display for BldSolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.memo)?;
+        let parity = |f: &mut fmt::Formatter| match self.parity.is_empty() {
+            true => Ok(()),
+            false => writeln!(f, "Parity: {}", format_moves(&self.parity)),
+        };
+        for step in &self.edges {
+            writeln!(f, "{}: {}", step.letter, format_moves(&step.moves))?;
+        }
+        if self.parity_first() {
+            parity(f)?;
+        }
+        for step in &self.corners {
+            writeln!(f, "{}: {}", step.letter, format_moves(&step.moves))?;
+        }
+        if !self.parity_first() {
+            parity(f)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+One way of sending the buffer piece to any target: edges or corners with a given swap algorithm.
+- `swap` exchanges the buffer piece with the helper piece, and also moves some other pieces (its side effect).
+- A letter is executed with setup + swap + undo setup when the setup keeps every piece moved by the
+  swap in place, otherwise with a special algorithm found by searching conjugates of `specials`.
+- The side effect is undone by every second swap, so the algorithm for a letter depends on the parity
+  of the number of letters already done.
+*/
+struct Shooter {
+    buffer: Vec<usize>,                       // Buffer piece stickers, the buffer sticker first
+    pieces: Vec<Vec<usize>>,                  // All pieces of this kind
+    letters: [usize; 24],
+    algs: HashMap<(usize, usize), Vec<Move>>, // (target sticker, parity) -> algorithm
+}
+
+impl Shooter {
+    fn new(buffer_sticker: usize, pieces: Vec<Vec<usize>>, letters: [usize; 24], swap: &str, specials: &[&str]) -> Self {
+        let swap = parse_moves(swap).expect("Invalid swap algorithm");
+        let swap_perm = alg_perm(&swap);
+
+        let buffer = Cube::stickers_from(buffer_sticker);
+        let helper = Cube::stickers_from(swap_perm[buffer_sticker] as usize);
+
+        let exchange = |target: &[usize]| -> Perm {
+            let mut perm = identity_perm();
+            for (&b, &t) in buffer.iter().zip(target.iter()) {
+                perm[b] = t as u8;
+                perm[t] = b as u8;
+            }
+            perm
+        };
+
+        // The swap without its buffer / helper exchange
+        let side = compose(&swap_perm, &exchange(&helper));
+
+        let mut shooter = Shooter { buffer: buffer.clone(), pieces: pieces.clone(), letters, algs: HashMap::new() };
+
+        // Effect wanted for each letter: back to the ideal state, exchange, then the side effect of one more swap
+        let mut wanted: HashMap<Perm, Vec<(usize, usize)>> = HashMap::new();
+        for &target in letters.iter().filter(|t| !buffer.contains(t)) {
+            for parity in 0..2 {
+                let before = if parity == 1 { side } else { identity_perm() };
+                let after = if parity == 1 { identity_perm() } else { side };
+                let effect = compose(&compose(&before, &exchange(&Cube::stickers_from(target))), &after);
+                wanted.entry(effect).or_default().push((target, parity));
+            }
+        }
+
+        // Setup + swap + undo setup, when the setup keeps the pieces moved by the swap in place
+        let protected: Vec<usize> = (0..Cube::CELL_COUNT)
+            .filter(|&p| swap_perm[p] as usize != p && !helper.contains(&p))
+            .collect();
+        let moves = move_perms(&all_moves());
+        for depth in 0..=SETUP_MAX_DEPTH {
+            for_each_sequence(depth, &moves, &mut Vec::new(), &identity_perm(), &mut |setup, perm| {
+                if protected.iter().any(|&p| perm[p] as usize != p) {
+                    return;
+                }
+                let effect = compose(&compose(perm, &swap_perm), &invert(perm));
+                for &key in wanted.get(&effect).into_iter().flatten() {
+                    shooter.algs.entry(key).or_insert_with(|| {
+                        let mut alg = setup.to_vec();
+                        alg.extend(swap.iter().copied());
+                        alg.extend(setup.iter().rev().map(|m| m.inverse()));
+                        simplify_moves(&alg)
+                    });
+                }
+            });
+        }
+
+        // Special cases: conjugates of the special algorithms, optionally followed or preceded by the swap
+        let specials: Vec<Vec<Move>> = specials.iter()
+            .map(|alg| parse_moves(alg).expect("Invalid special algorithm"))
+            .flat_map(|alg| [alg.clone(), alg.iter().rev().map(|m| m.inverse()).collect()])
+            .collect();
+        let special_perms: Vec<Perm> = specials.iter().map(|alg| alg_perm(alg)).collect();
+        for depth in 0..=SPECIAL_MAX_DEPTH {
+            if wanted.values().flatten().all(|key| shooter.algs.contains_key(key)) {
+                break;
+            }
+            for_each_sequence(depth, &moves, &mut Vec::new(), &identity_perm(), &mut |setup, perm| {
+                for (special, special_perm) in specials.iter().zip(special_perms.iter()) {
+                    let core = compose(&compose(perm, special_perm), &invert(perm));
+                    let mut core_moves = setup.to_vec();
+                    core_moves.extend(special.iter().copied());
+                    core_moves.extend(setup.iter().rev().map(|m| m.inverse()));
+
+                    let candidates = [
+                        (core, core_moves.clone()),
+                        (compose(&core, &swap_perm), [core_moves.clone(), swap.clone()].concat()),
+                        (compose(&swap_perm, &core), [swap.clone(), core_moves.clone()].concat()),
+                    ];
+                    for (effect, alg) in candidates {
+                        for &key in wanted.get(&effect).into_iter().flatten() {
+                            shooter.algs.entry(key).or_insert_with(|| simplify_moves(&alg));
+                        }
+                    }
+                }
+            });
+        }
+
+        shooter
+    }
+
+    fn letter(&self, sticker: usize) -> char {
+        let index = self.letters.iter().position(|&s| s == sticker).expect("Sticker must have a letter");
+        (b'A' + index as u8) as char
+    }
+
+    fn piece_of(&self, sticker: usize) -> &Vec<usize> {
+        self.pieces.iter().find(|p| p.contains(&sticker)).expect("Sticker must belong to a piece")
+    }
+
+    fn is_solved(cube: &Cube, piece: &[usize]) -> bool {
+        piece.iter().all(|&s| cube.state[s] as usize == s)
+    }
+
+    // Follow the buffer from a labelled cube, return the target stickers
+    // None when the pieces can't all be solved (twisted corner, flipped edge, ...)
+    fn trace(&self, cube: &Cube) -> Option<Vec<usize>> {
+        let mut ideal = *cube;
+        let mut targets = Vec::new();
+
+        loop {
+            if targets.len() > MAX_TARGETS {
+                return None;
+            }
+            let label = ideal.state[self.buffer[0]] as usize;
+            let target = if self.buffer.contains(&label) {
+                // Cycle break: the buffer holds its own piece, go to the first unsolved piece
+                let unsolved = self.letters.iter()
+                    .find(|&&s| !self.buffer.contains(&s) && !Self::is_solved(&ideal, self.piece_of(s)));
+                match unsolved {
+                    Some(&s) => s,
+                    None => break,
+                }
+            } else {
+                label
+            };
+
+            targets.push(target);
+            // Exchange the buffer piece with the target piece
+            let piece = self.piece_of(target);
+            let start = piece.iter().position(|&s| s == target).unwrap();
+            let mut next = ideal;
+            for k in 0..piece.len() {
+                let t = piece[(start + k) % piece.len()];
+                next.state[t] = ideal.state[self.buffer[k]];
+                next.state[self.buffer[k]] = ideal.state[t];
+            }
+            ideal = next;
+        }
+        if Self::is_solved(&ideal, &self.buffer) {
+            Some(targets)
+        } else {
+            None
+        }
+    }
+
+    // Algorithms for the targets, in order, alternating the parity
+    fn execute(&self, targets: &[usize]) -> Result<Vec<Step>, String> {
+        targets.iter().enumerate()
+            .map(|(i, &target)| {
+                let moves = self.algs.get(&(target, i % 2))
+                    .ok_or(format!("No algorithm for target {}", self.letter(target)))?;
+                Ok(Step { letter: self.letter(target), moves: moves.clone() })
+            })
+            .collect()
+    }
+}
+
+fn edge_pieces() -> Vec<Vec<usize>> {
+    Cube::EDGE_FACELETS.iter().map(|e| e.to_vec()).collect()
+}
+
+fn corner_pieces() -> Vec<Vec<usize>> {
+    Cube::CORNER_FACELETS.iter().map(|c| c.to_vec()).collect()
+}
+
+static OP_EDGES: OnceLock<Shooter> = OnceLock::new();
+static M2_EDGES: OnceLock<Shooter> = OnceLock::new();
+static OP_CORNERS_UBL: OnceLock<Shooter> = OnceLock::new();
+static OP_CORNERS_UFR: OnceLock<Shooter> = OnceLock::new();
+static R2_CORNERS: OnceLock<Shooter> = OnceLock::new();
+
+fn edge_shooter(execution: Execution) -> &'static Shooter {
+    match execution {
+        // T perm between U' and U: swaps UF and UB
+        Execution::OldPochmann => OP_EDGES.get_or_init(|| {
+            Shooter::new(7, edge_pieces(), EDGE_LETTERS, &format!("U' {} U", T_PERM), &[])
+        }),
+        // M2 swaps UF and DB, the M slice targets need their own algorithms
+        Execution::M2R2 => M2_EDGES.get_or_init(|| {
+            Shooter::new(7, edge_pieces(), EDGE_LETTERS, "M2", &[
+                "D2 M D2 M",
+                "M' D2 M' D2",
+                "U M D L2 D' M' D L2 D' U' M2",
+                "M2 U D L2 D' M D L2 D' M' U'",
+                "D B' L D' B M2 B' D L' B D'",
+            ])
+        }),
+    }
+}
+
+fn corner_shooter(execution: Execution, buffers: Buffers) -> Result<&'static Shooter, String> {
+    match (execution, buffers) {
+        // Y perm swaps UBL and DFR
+        (Execution::OldPochmann, Buffers::UblUf) => Ok(OP_CORNERS_UBL.get_or_init(|| {
+            Shooter::new(0, corner_pieces(), CORNER_LETTERS, Y_PERM, &[])
+        })),
+        (Execution::OldPochmann, Buffers::UfrUf) => Ok(OP_CORNERS_UFR.get_or_init(|| {
+            Shooter::new(8, corner_pieces(), CORNER_LETTERS, &format!("U2 {} U2", Y_PERM), &[])
+        })),
+        // R2 swaps UFR and DRB, the other R layer corners need 3-cycles
+        (Execution::M2R2, Buffers::UfrUf) => Ok(R2_CORNERS.get_or_init(|| {
+            Shooter::new(8, corner_pieces(), CORNER_LETTERS, "R2", &[A_PERM, CORNER_TWIST])
+        })),
+        (Execution::M2R2, Buffers::UblUf) => Err("R2 needs the buffer in the R layer, use the UFR buffer".to_string()),
+    }
+}
+
+// Pieces of the labelled cube that are in their slot but not oriented
+fn misoriented(cube: &Cube, pieces: &[Vec<usize>], names: &[&'static str]) -> Vec<&'static str> {
+    pieces.iter().zip(names.iter())
+        .filter(|(piece, _)| {
+            let in_slot = piece.iter().all(|&s| piece.contains(&(cube.state[s] as usize)));
+            in_slot && cube.state[piece[0]] as usize != piece[0]
+        })
+        .map(|(_, &name)| name)
+        .collect()
+}
+
+// Parity algorithms already found, by their permutation
+static PARITY_FIXES: OnceLock<Mutex<HashMap<Perm, Option<Vec<Move>>>>> = OnceLock::new();
+
+// Find a parity algorithm with the permutation `wanted`: a conjugated T perm followed by `tail`
+fn parity_fix(wanted: &Perm, tail: &str) -> Option<Vec<Move>> {
+    let fixes = PARITY_FIXES.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(fix) = fixes.lock().unwrap_or_else(|e| e.into_inner()).get(wanted) {
+        return fix.clone();
+    }
+    let t_perm = parse_moves(T_PERM).expect("Invalid T perm");
+    let tail = parse_moves(tail).expect("Invalid parity tail");
+    let t_perm_perm = alg_perm(&t_perm);
+    let tail_perm = alg_perm(&tail);
+    let moves = move_perms(&all_moves());
+    let mut found = None;
+    for depth in 0..=SETUP_MAX_DEPTH {
+        for_each_sequence(depth, &moves, &mut Vec::new(), &identity_perm(), &mut |setup, perm| {
+            if found.is_some() {
+                return;
+            }
+            let core = compose(&compose(perm, &t_perm_perm), &invert(perm));
+            if compose(&core, &tail_perm) == *wanted {
+                let mut alg = setup.to_vec();
+                alg.extend(t_perm.iter().copied());
+                alg.extend(setup.iter().rev().map(|m| m.inverse()));
+                alg.extend(tail.iter().copied());
+                found = Some(simplify_moves(&alg));
+            }
+        });
+        if found.is_some() {
+            break;
+        }
+    }
+    fixes.lock().unwrap_or_else(|e| e.into_inner()).insert(*wanted, found.clone());
+    found
+}
+
+/**
+Memo and execution for blindfolded solving.
+- Both memos are read from the scrambled cube. With parity, Old Pochmann swaps back the 2 corners
+  of the last T perm and the 2 edges the last Y perm will swap before the corners, M2/R2 undoes
+  the side effects of the last M2 and R2 at the end.
+- The execution is checked by applying it to the cube with `Cube::rotate`.
+*/
+pub fn solve_bld(cube: &Cube, buffers: Buffers, execution: Execution) -> Result<BldSolution, String> {
+    let labelled = cube.labelled().ok_or("Invalid cube: pieces can't be identified")?;
+    if (0..Cube::CELL_COUNT).any(|i| !labelled.state.contains(&(i as u8))) {
+        return Err("Invalid cube: a piece appears twice".to_string());
+    }
+
+    let edge_shooter = edge_shooter(execution);
+    let corner_shooter = corner_shooter(execution, buffers)?;
+
+    let flipped_edges = misoriented(&labelled, &edge_pieces(), &Cube::EDGE_NAMES);
+    let twisted_corners = misoriented(&labelled, &corner_pieces(), &Cube::CORNER_NAMES);
+
+    let edge_targets = edge_shooter.trace(&labelled).ok_or("Unsolvable cube: edges can't be solved")?;
+    let corner_targets = corner_shooter.trace(&labelled).ok_or("Unsolvable cube: corners can't be solved")?;
+    let edges = edge_shooter.execute(&edge_targets)?;
+    let corners = corner_shooter.execute(&corner_targets)?;
+    let parity = edge_targets.len() % 2 == 1;
+
+    let mut state = labelled;
+    let apply = |state: &mut Cube, steps: &[Step]| steps.iter().flat_map(|step| &step.moves).for_each(|m| m.apply(state));
+    apply(&mut state, &edges);
+    let mut parity_moves = Vec::new();
+    if parity && execution == Execution::OldPochmann {
+        // Back to the corners of the scramble, with the 2 edges the corner execution swaps back
+        let mut target = identity();
+        corners.iter().rev().flat_map(|step| step.moves.iter().rev()).for_each(|m| m.inverse().apply(&mut target));
+        let wanted = compose(&invert(&alg_perm_of_state(&state)), &alg_perm_of_state(&target));
+        parity_moves = parity_fix(&wanted, "").ok_or("No parity algorithm found")?;
+        parity_moves.iter().for_each(|m| m.apply(&mut state));
+    }
+    apply(&mut state, &corners);
+    if parity && execution == Execution::M2R2 {
+        // The side effects of one M2 and one R2 are left
+        parity_moves = parity_fix(&invert(&alg_perm_of_state(&state)), "M2 R2").ok_or("No parity algorithm found")?;
+        parity_moves.iter().for_each(|m| m.apply(&mut state));
+    }
+    if state != identity() {
+        return Err("Execution doesn't solve the cube".to_string());
+    }
+
+    let memo = Memo {
+        edges: edge_targets.iter().map(|&t| edge_shooter.letter(t)).collect(),
+        corners: corner_targets.iter().map(|&t| corner_shooter.letter(t)).collect(),
+        flipped_edges,
+        twisted_corners,
+        parity,
+    };
+    Ok(BldSolution { memo, edges, corners, parity: parity_moves, execution })
+}
+
+// The permutation that turns the solved labelled cube into `state`
+fn alg_perm_of_state(state: &Cube) -> Perm {
+    let mut perm = [0; Cube::CELL_COUNT];
+    for (to, &from) in state.state.iter().enumerate() {
+        perm[from as usize] = to as u8;
+    }
+    perm
+}
@@ -0,0 +1,548 @@
+/*!
+* Case database and recognition: OLL, PLL, CMLL, COLL and F2L
+* - OLL cases are numbered 1 to 57 and PLL cases named Aa to Z, each with one algorithm.
+* - CMLL cases have the names and numbers of the standard sheets: the shape of the corner
+*   orientation (O, H, Pi, U, T, S, As, L) and a number, or the shape and the recognition name
+*   ("Pi 3" is "Pi X Checkerboard").
+* - COLL cases are the same 40 cases without O (with the corners oriented what is left is a PLL),
+*   with the same names and algorithms that keep the edges oriented.
+* - F2L cases are the 41 cases of the front right pair, with the standard numbers 1 to 41.
+* - A case is recognized under every AUF: the result gives the U turn to do before the algorithm,
+*   the one after it, and the stickers that were read.
+*/
+
+use crate::cube::{Cube, Face, RotationDirection};
+use crate::notation::{apply_moves, invert_moves, parse_moves, Move};
+use crate::stage::{identity, is_home, move_perm, piece_stickers};
+use std::fmt;
+use std::sync::OnceLock;
+
+// Pieces around the last layer (indices into Cube::CORNER_FACELETS / Cube::EDGE_FACELETS)
+const LL_PIECES: [usize; 4] = [0, 1, 2, 3];
+const F2L_CORNERS: [usize; 4] = [4, 5, 6, 7];
+const F2L_EDGES: [usize; 8] = [4, 5, 6, 7, 8, 9, 10, 11];
+const BLOCK_EDGES: [usize; 6] = [4, 6, 8, 9, 10, 11]; // DR, DL, FR, FL, BL, BR
+const PAIR_CORNER: usize = 4; // DFR
+const PAIR_EDGE: usize = 8; // FR
+const OTHER_CORNERS: [usize; 3] = [5, 6, 7];
+const OTHER_EDGES: [usize; 7] = [4, 5, 6, 7, 9, 10, 11]; // Cross and the other slots
+
+// Stickers on the top row of the side faces
+const SIDE_STICKERS: [usize; 12] = [18, 19, 20, 27, 28, 29, 36, 37, 38, 45, 46, 47];
+
+pub const OLL_CASES: [&str; 57] = [
+    "R U2 R2 F R F' U2 R' F R F'",
+    "F R U R' U' F' f R U R' U' f'",
+    "f R U R' U' f' U' F R U R' U' F'",
+    "f R U R' U' f' U F R U R' U' F'",
+    "r' U2 R U R' U r",
+    "r U2 R' U' R U' r'",
+    "r U R' U R U2 r'",
+    "r' U' R U' R' U2 r",
+    "R U R' U' R' F R2 U R' U' F'",
+    "R U R' U R' F R F' R U2 R'",
+    "r U R' U R' F R F' R U2 r'",
+    "M' R' U' R U' R' U2 R U' R r'",
+    "F U R U' R2 F' R U R U' R'",
+    "R' F R U R' F' R F U' F'",
+    "r' U' r R' U' R U r' U r",
+    "r U r' R U R' U' r U' r'",
+    "R U R' U R' F R F' U2 R' F R F'",
+    "r U R' U R U2 r2 U' R U' R' U2 r",
+    "r' R U R U R' U' M' R' F R F'",
+    "r U R' U' M2 U R U' R' U' M'",
+    "R U2 R' U' R U R' U' R U' R'",
+    "R U2 R2 U' R2 U' R2 U2 R",
+    "R2 D' R U2 R' D R U2 R",
+    "r U R' U' r' F R F'",
+    "F' r U R' U' r' F R",
+    "R U2 R' U' R U' R'",
+    "R U R' U R U2 R'",
+    "r U R' U' M U R U' R'",
+    "R U R' U' R U' R' F' U' F R U R'",
+    "F R' F R2 U' R' U' R U R' F2",
+    "R' U' F U R U' R' F' R",
+    "L U F' U' L' U L F L'",
+    "R U R' U' R' F R F'",
+    "R U R2 U' R' F R U R U' F'",
+    "R U2 R2 F R F' R U2 R'",
+    "L' U' L U' L' U L U L F' L' F",
+    "F R' F' R U R U' R'",
+    "R U R' U R U' R' U' R' F R F'",
+    "L F' L' U' L U F U' L'",
+    "R' F R U R' U' F' U R",
+    "R U R' U R U2 R' F R U R' U' F'",
+    "R' U' R U' R' U2 R F R U R' U' F'",
+    "F' U' L' U L F",
+    "F U R U' R' F'",
+    "F R U R' U' F'",
+    "R' U' R' F R F' U R",
+    "R' U' R' F R F' R' F R F' U R",
+    "F R U R' U' R U R' U' F'",
+    "r U' r2 U r2 U r2 U' r",
+    "r' U r2 U' r2 U' r2 U r'",
+    "F U R U' R' U R U' R' F'",
+    "R U R' U R U' B U' B' R'",
+    "l' U2 L U L' U' L U L' U l",
+    "r U2 R' U' R U R' U' R U' r'",
+    "R' F R U R U' R2 F' R2 U' R' U R U R'",
+    "r' U' r U' R' U R U' R' U R r' U r",
+    "R U R' U' M' U R U' r'",
+];
+
+// PLL cases by name, each with one algorithm that solves it
+pub const PLL_CASES: [(&str, &str); 21] = [
+    ("Aa", "R' F R' B2 R F' R' B2 R2"),
+    ("Ab", "R2 B2 R F R' B2 R F' R"),
+    ("E", "R B' R' F R B R' F' R B R' F R B' R' F'"),
+    ("F", "R' U' F' R U R' U' R' F R2 U' R' U' R U R' U R"),
+    ("Ga", "R2 U R' U R' U' R U' R2 U' D R' U R D'"),
+    ("Gb", "R' U' R U D' R2 U R' U R U' R U' R2 D"),
+    ("Gc", "R2 U' R U' R U R' U R2 U D' R U' R' D"),
+    ("Gd", "R U R' U' D R2 U' R U' R' U R' U R2 D'"),
+    ("H", "M2 U M2 U2 M2 U M2"),
+    ("Ja", "R' U L' U2 R U' R' U2 R L"),
+    ("Jb", "R U R' F' R U R' U' R' F R2 U' R'"),
+    ("Na", "R U R' U R U R' F' R U R' U' R' F R2 U' R' U2 R U' R'"),
+    ("Nb", "R' U R U' R' F' U' F R U R' F R' F' R U' R"),
+    ("Ra", "R U' R' U' R U R D R' U' R D' R' U2 R'"),
+    ("Rb", "R2 F R U R U' R' F' R U2 R' U2 R"),
+    ("T", "R U R' U' R' F R2 U' R' U' R U R' F'"),
+    ("Ua", "R U' R U R U R U' R' U' R2"),
+    ("Ub", "R2 U R U R' U' R' U' R' U R'"),
+    ("V", "R' U R' U' B' R' B2 U' B' U B' R B R"),
+    ("Y", "F R U' R' U' R U R' F' R U R' U' R' F R F'"),
+    ("Z", "M2 U M2 U M' U2 M2 U2 M' U2"),
+];
+
+// CMLL cases by shape, numbered in this order: shape, recognition name and algorithm
+pub const CMLL_CASES: [(&str, &str, &str); 42] = [
+    ("O", "Adjacent", "R U R' F' R U R' U' R' F R2 U' R' U'"),
+    ("O", "Diagonal", "F R U' R' U' R U R' F' R U R' U' R' F R F'"),
+    ("H", "Columns", "R U2 R' U' R U R' U' R U' R'"),
+    ("H", "Rows", "F R U R' U' R U R' U' R U R' U' F'"),
+    ("H", "Column", "U R U2 R2 F R F' U2 R' F R F'"),
+    ("H", "Row", "r U' r2 D' r U' r' D r2 U r'"),
+    ("Pi", "Right Bar", "F R U R' U' R U R' U' F'"),
+    ("Pi", "Back Slash", "U F R' F' R U2 R U' R' U R U2 R'"),
+    ("Pi", "X Checkerboard", "U' R' F R U F U' R U R' U' F'"),
+    ("Pi", "Forward Slash", "R U2 R' U' R U R' U2 R' F R F'"),
+    ("Pi", "Columns", "U' r U' r2 D' r U r' D r2 U r'"),
+    ("Pi", "Left Bar", "U' R' U' R' F R F' R U' R' U2 R"),
+    ("U", "Forward Slash", "U2 R2 D R' U2 R D' R' U2 R'"),
+    ("U", "Back Slash", "R2 D' R U2 R' D R U2 R"),
+    ("U", "Front Row", "R2 F U' F U F2 R2 U' R' F R"),
+    ("U", "Rows", "U' F R2 D R' U R D' R2 U' F'"),
+    ("U", "X Checkerboard", "U2 r U' r' U r' D' r U' r' D r"),
+    ("U", "Back Row", "U' F R U R' U' F'"),
+    ("T", "Left Bar", "U' R U R' U' R' F R F'"),
+    ("T", "Right Bar", "U L' U' L U L F' L' F"),
+    ("T", "Rows", "F R' F R2 U' R' U' R U R' F2"),
+    ("T", "Front Row", "r' U r U2 R2 F R F' R"),
+    ("T", "Back Row", "r' D' r U r' D r U' r U r'"),
+    ("T", "Columns", "U2 r2 D' r U r' D r2 U' r' U' r"),
+    ("S", "Left Bar", "U R U R' U R U2 R'"),
+    ("S", "X Checkerboard", "U L' U2 L U2 L F' L' F"),
+    ("S", "Forward Slash", "U F R' F' R U2 R U2 R'"),
+    ("S", "Columns", "R U R' U R U' R D R' U' R D' R2"),
+    ("S", "Right Bar", "R U R' U R' F R F' R U2 R'"),
+    ("S", "Back Slash", "U R U' L' U R' U' L"),
+    ("As", "Right Bar", "U' L' U' L U' L' U2 L"),
+    ("As", "Columns", "U' R2 D R' U R D' R' U R' U' R U' R'"),
+    ("As", "Back Slash", "U' F' L F L' U2 L' U2 L"),
+    ("As", "X Checkerboard", "U' R U2 R' U2 R' F R F'"),
+    ("As", "Forward Slash", "U' L' U R U' L U R'"),
+    ("As", "Left Bar", "R' U' R U' R' U R' F R F' U R"),
+    ("L", "Mirror", "F R U' R' U' R U R' F'"),
+    ("L", "Inverse", "F R' F' R U R U' R'"),
+    ("L", "Pure", "R U2 R' U' R U R' U' R U R' U' R U' R'"),
+    ("L", "Front Commutator", "R U2 R D R' U2 R D' R2"),
+    ("L", "Diag", "R' U' R U R' F' R U R' U' R' F R2"),
+    ("L", "Back Commutator", "R' U2 R' D' R U2 R' D R2"),
+];
+
+// COLL cases in the order of CMLL_CASES without O, each algorithm keeps the edges oriented
+pub const COLL_CASES: [(&str, &str, &str); 40] = [
+    ("H", "Columns", "R U2 R' U' R U R' U' R U' R'"),
+    ("H", "Rows", "F R U R' U' R U R' U' R U R' U' F'"),
+    ("H", "Column", "U' R' U2 R U F R' U R F' U F U2 F'"),
+    ("H", "Row", "U L U L' U L U R' U L' U' R"),
+    ("Pi", "Right Bar", "U R' U2 R2 U R2 U R2 U2 R'"),
+    ("Pi", "Back Slash", "U R U2 R' U' R U' R2 U L U' R U L'"),
+    ("Pi", "X Checkerboard", "U F U2 F' U' F R' U' R F' U' R' U2 R"),
+    ("Pi", "Forward Slash", "U' R U R' U F2 R U2 R' U2 R' F2 R"),
+    ("Pi", "Columns", "U' L U' R' U L' U R U R' U R"),
+    ("Pi", "Left Bar", "F' R U F2 U R' U' R F2 U' R' F"),
+    ("U", "Forward Slash", "U2 R2 D R' U2 R D' R' U2 R'"),
+    ("U", "Back Slash", "R2 D' R U2 R' D R U2 R"),
+    ("U", "Front Row", "R' U' R U' R' U2 R2 U R' U R U2 R'"),
+    ("U", "Rows", "R' L U L2 U2 R U' R' U2 R L2 U2 L'"),
+    ("U", "X Checkerboard", "R' U2 R U F U' R' U2 R U F'"),
+    ("U", "Back Row", "R' U2 R F U' R' U' R U F'"),
+    ("T", "Left Bar", "U' R U R' U' L' U R U' R' L"),
+    ("T", "Right Bar", "U' R' U' R F R' U R U' F'"),
+    ("T", "Rows", "U2 R' U2 R U R' U R2 U2 R' U' R U' R'"),
+    ("T", "Front Row", "L U' R U R' L' U2 R U R'"),
+    ("T", "Back Row", "U2 F U' R' U2 R U F' U' R' U2 R"),
+    ("T", "Columns", "U2 R' U F' R' U2 R U2 F U' R' U R2"),
+    ("S", "Left Bar", "U R U R' U R U2 R'"),
+    ("S", "X Checkerboard", "U' R' U2 R U F R' U R U' F'"),
+    ("S", "Forward Slash", "U R L' U R' U' L U2 R U2 R'"),
+    ("S", "Columns", "R U R' U R U' R D R' U' R D' R2"),
+    ("S", "Right Bar", "U' R U R' U L' U R U' L U2 R'"),
+    ("S", "Back Slash", "U R U' L' U R' U' L"),
+    ("As", "Right Bar", "U' L' U' L U' L' U2 L"),
+    ("As", "Columns", "U' R2 D R' U R D' R' U R' U' R U' R'"),
+    ("As", "Back Slash", "U F U R' U' R F' U' R' U2 R"),
+    ("As", "X Checkerboard", "R U2 R' U2 L' U R U' R' L"),
+    ("As", "Forward Slash", "U' L' U R U' L U R'"),
+    ("As", "Left Bar", "R' U' R U' L U' R' U L' U2 R"),
+    ("L", "Mirror", "U2 F U R' U' R F' R' U R"),
+    ("L", "Inverse", "R' U' R U F U' R' U R U F'"),
+    ("L", "Pure", "R U2 R' U' R U R' U' R U R' U' R U' R'"),
+    ("L", "Front Commutator", "R U2 R D R' U2 R D' R2"),
+    ("L", "Diag", "R' U' R U R' F' R U R' U' R' F R2"),
+    ("L", "Back Commutator", "R' U2 R' D' R U2 R' D R2"),
+];
+
+// F2L cases of the front right pair with the cross and the other slots solved, in the standard
+// numbering: both pieces in the U layer 1 to 24, the corner in the slot 25 to 30, the edge in
+// the slot 31 to 36, both in the slot 37 to 41.
+pub const F2L_CASES: [&str; 41] = [
+    "U R U' R'", "U' F' U F",
+    "F' U' F", "R U R'",
+    "U' R U R' U2 R U' R'", "U F' U' F U2 F' U F",
+    "U' R U2 R' U2 R U' R'", "U F' U2 F U2 F' U F",
+    "U F' U' F U' F' U' F", "U' R U R' U R U R'",
+    "U' R U2 R' U F' U' F", "U F' U2 F U' R U R'",
+    "U F' U F U' F' U' F", "U' R U' R' U R U R'",
+    "F' U F U2 R U R'", "R U' R' U2 F' U' F",
+    "R U2 R' U' R U R'", "F' U2 F U F' U' F",
+    "U R U2 R' U R U' R'", "U' F' U2 F U' F' U F",
+    "U2 R U R' U R U' R'", "U2 F' U' F U' F' U F",
+    "U2 R2 U2 R' U' R U' R2", "U2 F2 U2 F U F' U F2",
+    "U' R' F R F' R U R'", "U R U' R' U' F' U F",
+    "R U' R' U R U' R'", "F' U F U' F' U F",
+    "F' U' F U F' U' F", "R U R' U' R U R'",
+    "U' R' F R F' R U' R'", "U R U' R' U R U' R' U R U' R'",
+    "U' R U' R' U2 R U' R'", "U R U R' U2 R U R'",
+    "U' R U R' U F' U' F", "U F' U' F U' R U R'",
+    "R2 U2 F R2 F' U2 R' U R'",
+    "R U' R' U' R U R' U2 R U' R'", "R U' R' U R U2 R' U R U' R'",
+    "r U' r' U2 r U r' R U R'", "R U F R U R' U' F' R'",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CaseSet {
+    Oll,  // Orient the last layer
+    Pll,  // Permute the last layer
+    Cmll, // Corners of the last layer, the M slice is free (Roux)
+    Coll, // Corners of the last layer with its edges oriented
+    F2l,  // Front right pair of the first two layers
+}
+
+impl CaseSet {
+    pub const ALL: [CaseSet; 5] = [CaseSet::Oll, CaseSet::Pll, CaseSet::Cmll, CaseSet::Coll, CaseSet::F2l];
+
+    // "OLL", "pll", ...
+    pub fn from_name(name: &str) -> Option<CaseSet> {
+        CaseSet::ALL.into_iter().find(|set| set.to_string().eq_ignore_ascii_case(name))
+    }
+}
+
+impl fmt::Display for CaseSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            CaseSet::Oll => "OLL",
+            CaseSet::Pll => "PLL",
+            CaseSet::Cmll => "CMLL",
+            CaseSet::Coll => "COLL",
+            CaseSet::F2l => "F2L",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/**
+One case of the database.
+- `reference` is the labelled cube of the case as the algorithm solves it, without AUF.
+- `setup` leads from the solved cube to the reference.
+- `number` is the number of the case in its set (OLL, PLL, F2L) or in its shape (CMLL, COLL).
+*/
+#[derive(Clone, Debug)]
+pub struct Case {
+    pub set: CaseSet,
+    pub name: String,
+    pub number: usize,
+    pub recognition: Option<&'static str>, // CMLL and COLL: "X Checkerboard", ...
+    pub algorithm: Option<Vec<Move>>,
+    pub setup: Vec<Move>,
+    pub reference: Cube,
+}
+
+impl Case {
+    // The case in the standard colors
+    pub fn cube(&self) -> Cube {
+        Cube { state: self.reference.state.map(|label| label / Cube::FACE_SIZE as u8) }
+    }
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.set, self.name)
+    }
+}
+
+/**
+A recognized case: doing `pre_auf`, the case algorithm (or any algorithm for the reference
+state) and `post_auf` solves it.
+- `stickers` are the stickers read to recognize the case, on the cube as it was given.
+*/
+#[derive(Clone, Debug)]
+pub struct Recognition {
+    pub case: &'static Case,
+    pub pre_auf: Option<Move>,
+    pub post_auf: Option<Move>,
+    pub stickers: Vec<usize>,
+}
+
+impl Recognition {
+    // Whole algorithm with its AUFs, when the case has one
+    pub fn moves(&self) -> Option<Vec<Move>> {
+        let algorithm = self.case.algorithm.as_ref()?;
+        Some(self.pre_auf.into_iter().chain(algorithm.iter().copied()).chain(self.post_auf).collect())
+    }
+}
+
+impl fmt::Display for Recognition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.case)?;
+        if let Some(recognition) = self.case.recognition {
+            write!(f, " ({})", recognition)?;
+        }
+        if let Some(m) = self.pre_auf {
+            write!(f, ", AUF before: {}", m)?;
+        }
+        if let Some(m) = self.post_auf {
+            write!(f, ", AUF after: {}", m)?;
+        }
+        Ok(())
+    }
+}
+
+// U turns: none, U, U2 and U'
+pub fn aufs() -> [Option<Move>; 4] {
+    [
+        None,
+        Some(Move::face(Face::U, RotationDirection::Clockwise)),
+        Some(Move::face(Face::U, RotationDirection::DoubleTurn)),
+        Some(Move::face(Face::U, RotationDirection::CounterClockwise)),
+    ]
+}
+
+// The cube after an AUF, unchanged for None
+pub fn apply_auf(cube: &Cube, auf: Option<Move>) -> Cube {
+    let mut cube = *cube;
+    if let Some(m) = auf {
+        m.apply(&mut cube);
+    }
+    cube
+}
+
+// Labelled cube of the case an algorithm solves
+fn case_of(algorithm: &[Move]) -> Cube {
+    let mut cube = identity();
+    apply_moves(&mut cube, &invert_moves(algorithm));
+    cube
+}
+
+fn ll_corner_stickers() -> Vec<usize> {
+    piece_stickers(&LL_PIECES, &[])
+}
+
+fn ll_stickers() -> Vec<usize> {
+    piece_stickers(&LL_PIECES, &LL_PIECES)
+}
+
+fn pair_stickers() -> Vec<usize> {
+    piece_stickers(&[PAIR_CORNER], &[PAIR_EDGE])
+}
+
+// Where the stickers of the front right pair are
+fn pair_key(cube: &Cube) -> Vec<usize> {
+    pair_stickers().iter()
+        .map(|&label| cube.state.iter().position(|&l| l as usize == label).unwrap_or(label))
+        .collect()
+}
+
+// Which last layer stickers show the U color
+fn orientation_key(cube: &Cube, stickers: &[usize]) -> Vec<bool> {
+    stickers.iter().map(|&s| (cube.state[s] as usize) < Cube::FACE_SIZE).collect()
+}
+
+// Labels of the stickers after the post-AUF `turns`: a state solved up to that U turn
+fn permutation_key(cube: &Cube, stickers: &[usize], turns: usize) -> Vec<u8> {
+    let u = move_perm(&Move::face(Face::U, RotationDirection::Clockwise));
+    stickers.iter()
+        .map(|&s| (0..turns).fold(cube.state[s], |label, _| u[label as usize]))
+        .collect()
+}
+
+// The cases of one set that are reached from `reference` by some AUF before and after
+fn same_case(cube: &Cube, reference: &Cube, set: CaseSet) -> Option<(Option<Move>, Option<Move>)> {
+    let stickers = match set {
+        CaseSet::Oll | CaseSet::Pll => ll_stickers(),
+        CaseSet::Cmll | CaseSet::Coll => ll_corner_stickers(),
+        CaseSet::F2l => pair_stickers(),
+    };
+    for pre_auf in aufs() {
+        let turned = apply_auf(cube, pre_auf);
+        // The slot is solved with no U turn after the algorithm
+        if set == CaseSet::F2l {
+            if pair_key(&turned) == pair_key(reference) {
+                return Some((pre_auf, None));
+            }
+            continue;
+        }
+        if set == CaseSet::Oll {
+            if orientation_key(&turned, &stickers) == orientation_key(reference, &stickers) {
+                return Some((pre_auf, None));
+            }
+            continue;
+        }
+        let key = permutation_key(&turned, &stickers, 0);
+        for (turns, post_auf) in aufs().into_iter().enumerate() {
+            if key == permutation_key(reference, &stickers, turns) {
+                return Some((pre_auf, post_auf));
+            }
+        }
+    }
+    None
+}
+
+static DATABASE: OnceLock<Vec<Case>> = OnceLock::new();
+
+fn case(set: CaseSet, name: String, number: usize, recognition: Option<&'static str>, algorithm: &str) -> Case {
+    let algorithm = parse_moves(algorithm).expect("Invalid case algorithm");
+    let reference = case_of(&algorithm);
+    Case { set, name, number, recognition, setup: invert_moves(&algorithm), algorithm: Some(algorithm), reference }
+}
+
+// Corner cases numbered from 1 in each shape
+fn corner_cases(set: CaseSet, table: &'static [(&'static str, &'static str, &'static str)]) -> Vec<Case> {
+    table.iter().enumerate()
+        .map(|(i, &(shape, recognition, algorithm))| {
+            let number = table[..i].iter().filter(|(other, _, _)| *other == shape).count() + 1;
+            case(set, format!("{} {}", shape, number), number, Some(recognition), algorithm)
+        })
+        .collect()
+}
+
+// Every case of the 5 sets
+pub fn cases() -> &'static [Case] {
+    DATABASE.get_or_init(|| {
+        let mut cases: Vec<Case> = OLL_CASES.iter().enumerate()
+            .map(|(i, algorithm)| case(CaseSet::Oll, (i + 1).to_string(), i + 1, None, algorithm))
+            .collect();
+        cases.extend(PLL_CASES.iter().enumerate().map(|(i, (name, algorithm))| case(CaseSet::Pll, name.to_string(), i + 1, None, algorithm)));
+        cases.extend(corner_cases(CaseSet::Cmll, &CMLL_CASES));
+        cases.extend(corner_cases(CaseSet::Coll, &COLL_CASES));
+        cases.extend(F2L_CASES.iter().enumerate().map(|(i, algorithm)| case(CaseSet::F2l, (i + 1).to_string(), i + 1, None, algorithm)));
+        cases
+    })
+}
+
+// The cases of one set
+pub fn cases_of(set: CaseSet) -> impl Iterator<Item = &'static Case> {
+    cases().iter().filter(move |case| case.set == set)
+}
+
+// A case by its set and name, e.g. (Oll, "27"), (Pll, "Ua"), (Cmll, "Pi 3") or (Cmll, "Pi X Checkerboard")
+pub fn find_case(set: CaseSet, name: &str) -> Option<&'static Case> {
+    let words = |text: &str| text.split_whitespace().map(str::to_lowercase).collect::<Vec<_>>();
+    let name = words(name);
+    cases_of(set).find(|case| {
+        let shape = case.name.split(' ').next().unwrap_or_default();
+        words(&case.name) == name || case.recognition.is_some_and(|recognition| words(&format!("{} {}", shape, recognition)) == name)
+    })
+}
+
+// A case by its full name: "OLL 27", "PLL Ua", "CMLL T 3", "COLL S Left Bar", "F2L 12", or a PLL name alone
+pub fn case_by_id(id: &str) -> Option<&'static Case> {
+    let (set, name) = id.trim().split_once(' ').unwrap_or(("PLL", id.trim()));
+    find_case(CaseSet::from_name(set)?, name.trim())
+}
+
+/**
+Recognize the case of a cube for one set.
+- OLL, PLL and COLL need the first two layers solved, CMLL only the two blocks, F2L the cross
+  and every slot but the front right one.
+- PLL needs the last layer oriented, COLL its edges oriented.
+- Returns an error when the cube can't be labelled, the pieces that must be solved aren't, or
+  the last layer is already done for this set.
+*/
+pub fn recognize(cube: &Cube, set: CaseSet) -> Result<Recognition, String> {
+    let labelled = cube.labelled().ok_or("Invalid cube: pieces can't be identified")?;
+    let solved_pieces = match set {
+        CaseSet::Cmll => piece_stickers(&F2L_CORNERS, &BLOCK_EDGES),
+        CaseSet::F2l => piece_stickers(&OTHER_CORNERS, &OTHER_EDGES),
+        _ => piece_stickers(&F2L_CORNERS, &F2L_EDGES),
+    };
+    if !is_home(&labelled, &solved_pieces) {
+        return Err(match set {
+            CaseSet::F2l => "F2L needs the cross and the other three slots solved".to_string(),
+            _ => format!("{} needs the first two layers solved", set),
+        });
+    }
+    if set == CaseSet::F2l && is_home(&labelled, &pair_stickers()) {
+        return Err("No F2L case: the front right slot is already solved".to_string());
+    }
+    let oriented = |stickers: &[usize]| stickers.iter().all(|&s| (labelled.state[s] as usize) < Cube::FACE_SIZE);
+    let u_edges: Vec<usize> = LL_PIECES.iter().map(|&e| Cube::EDGE_FACELETS[e][0]).collect();
+    let u_stickers: Vec<usize> = ll_stickers().into_iter().filter(|&s| s < Cube::FACE_SIZE).collect();
+    match set {
+        CaseSet::Pll if !oriented(&u_stickers) => return Err("PLL needs the last layer oriented".to_string()),
+        CaseSet::Coll if !oriented(&u_edges) => return Err("COLL needs the last layer edges oriented".to_string()),
+        CaseSet::Coll if oriented(&u_stickers) => return Err("No COLL case: the last layer is oriented, what is left is a PLL".to_string()),
+        _ => {}
+    }
+
+    for case in cases_of(set) {
+        if let Some((pre_auf, post_auf)) = same_case(&labelled, &case.reference, set) {
+            let stickers = match set {
+                // The stickers showing the U color
+                CaseSet::Oll => ll_stickers().into_iter().filter(|&s| cube.state[s] == cube.state[Cube::CENTERS[0]]).collect(),
+                CaseSet::Pll => SIDE_STICKERS.to_vec(),
+                CaseSet::Cmll | CaseSet::Coll => ll_corner_stickers(),
+                // Where the pair is
+                CaseSet::F2l => pair_key(&labelled),
+            };
+            return Ok(Recognition { case, pre_auf, post_auf, stickers });
+        }
+    }
+    Err(format!("No {} case: this part of the last layer is already solved", set))
+}
+
+/**
+Moves that finish one set's part of a labelled cube, as the method solvers use the database.
+- `done` tells when the part is finished. A U turn alone is tried first, then the case is
+  recognized and solved by its algorithm with its AUFs.
+- The centers are taken as solved, so the M slice may be off (Roux).
+*/
+pub fn solve_case(labelled: &Cube, set: CaseSet, done: &dyn Fn(&Cube) -> bool) -> Result<Vec<Move>, String> {
+    if let Some(auf) = aufs().into_iter().find(|&auf| done(&apply_auf(labelled, auf))) {
+        return Ok(auf.into_iter().collect());
+    }
+    let mut colors = Cube { state: labelled.state.map(|label| label / Cube::FACE_SIZE as u8) };
+    for (face, &center) in Cube::CENTERS.iter().enumerate() {
+        colors.state[center] = face as u8;
+    }
+    let recognition = recognize(&colors, set)?;
+    let moves = recognition.moves().ok_or_else(|| format!("{} has no algorithm", recognition.case))?;
+    let mut solved = *labelled;
+    apply_moves(&mut solved, &moves);
+    match done(&solved) {
+        true => Ok(moves),
+        false => Err(format!("{} doesn't solve the {} stage", recognition.case, set)),
+    }
+}
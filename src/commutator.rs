@@ -0,0 +1,207 @@
+/*!
+* 3-style commutator finder for blindfolded solving
+* - A commutator [A, B] = A B A' B' and a conjugate [C: [A, B]] = C A B A' B' C'.
+* - The search looks for commutators that cycle exactly 3 pieces: the buffer piece goes to the
+*   first target, the first target piece to the second target, and the second target to the buffer.
+* - Pure commutators use one single move (the interchange) and an insertion of up to
+*   INSERTION_MAX_DEPTH moves. Setup moves C are added in front when no pure commutator exists.
+*/
+
+use crate::cube::{Cube, Piece, RotationDirection};
+use crate::notation::{all_moves, format_moves, simplify_moves, Layer, Move};
+use crate::stage::{compose, for_each_sequence, identity_perm, invert, move_perms, Perm};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+const INSERTION_MAX_DEPTH: usize = 3;
+const SETUP_MAX_DEPTH: usize = 3;
+
+// Most stickers a 3-cycle of corners can move
+const MAX_MOVED_STICKERS: usize = 9;
+
+/**
+Cost of the finger tricks of an algorithm.
+- Each quarter turn costs the value of its layer, a half turn costs `half_turn` more.
+- The default prefers R, U and the M slice, then L, F and D, and B, E, S last.
+*/
+#[derive(Clone, Debug)]
+pub struct FingerCost {
+    pub faces: [u32; 6],  // U, D, R, L, F, B
+    pub slices: [u32; 3], // M, E, S
+    pub half_turn: u32,
+}
+
+impl Default for FingerCost {
+    fn default() -> Self {
+        FingerCost { faces: [1, 2, 1, 2, 2, 3], slices: [2, 3, 3], half_turn: 1 }
+    }
+}
+
+impl FingerCost {
+    pub fn cost(&self, moves: &[Move]) -> u32 {
+        moves.iter()
+            .map(|m| {
+                let layer = match m.layer {
+                    Layer::Face(face) => self.faces[face as usize / Cube::FACE_SIZE],
+                    Layer::Slice(slice) => self.slices[slice as usize],
+                };
+                match m.direction {
+                    RotationDirection::DoubleTurn => layer + self.half_turn,
+                    _ => layer,
+                }
+            })
+            .sum()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Commutator {
+    pub setup: Vec<Move>,
+    pub a: Vec<Move>,
+    pub b: Vec<Move>,
+    pub move_count: usize, // After cancellations
+    pub cost: u32,
+}
+
+impl Commutator {
+    fn new(setup: Vec<Move>, a: Vec<Move>, b: Vec<Move>, cost: &FingerCost) -> Self {
+        let mut commutator = Commutator { setup, a, b, move_count: 0, cost: 0 };
+        let moves = commutator.moves();
+        commutator.move_count = moves.len();
+        commutator.cost = cost.cost(&moves);
+        commutator
+    }
+
+    // Expanded moves, merged where they cancel
+    pub fn moves(&self) -> Vec<Move> {
+        let inverse = |moves: &[Move]| moves.iter().rev().map(|m| m.inverse()).collect::<Vec<_>>();
+        let moves = [
+            self.setup.clone(),
+            self.a.clone(),
+            self.b.clone(),
+            inverse(&self.a),
+            inverse(&self.b),
+            inverse(&self.setup),
+        ]
+        .concat();
+        simplify_moves(&moves)
+    }
+}
+
+impl fmt::Display for Commutator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pure = format!("[{}, {}]", format_moves(&self.a), format_moves(&self.b));
+        if self.setup.is_empty() {
+            write!(f, "{}", pure)
+        } else {
+            write!(f, "[{}: {}]", format_moves(&self.setup), pure)
+        }
+    }
+}
+
+// Effect of every pure commutator that moves at most 3 pieces -> its (A, B) parts
+type PureTable = HashMap<Perm, Vec<(Vec<Move>, Vec<Move>)>>;
+
+static PURE: OnceLock<PureTable> = OnceLock::new();
+
+fn pure_commutators() -> &'static PureTable {
+    PURE.get_or_init(|| {
+        let moves = move_perms(&all_moves());
+        let mut table = PureTable::new();
+        for depth in 1..=INSERTION_MAX_DEPTH {
+            for_each_sequence(depth, &moves, &mut Vec::new(), &identity_perm(), &mut |insertion, perm| {
+                let perm_inv = invert(perm);
+                for (interchange, m_perm) in &moves {
+                    let m_inv = invert(m_perm);
+                    // [insertion, interchange] and its inverse [interchange, insertion]
+                    let effect = compose(&compose(&compose(perm, m_perm), &perm_inv), &m_inv);
+                    let moved = effect.iter().enumerate().filter(|&(p, &q)| p != q as usize).count();
+                    if moved == 0 || moved > MAX_MOVED_STICKERS {
+                        continue;
+                    }
+                    table.entry(effect).or_default().push((insertion.to_vec(), vec![*interchange]));
+                    table.entry(invert(&effect)).or_default().push((vec![*interchange], insertion.to_vec()));
+                }
+            });
+        }
+        table
+    })
+}
+
+/**
+Find commutators that cycle the buffer piece to the first target and the first target to the second.
+- `buffer`, `first` and `second` are sticker indices of 3 different corners or 3 different edges.
+  The sticker at `buffer` goes to `first`, the sticker at `first` goes to `second`.
+- Returns up to `max_results` commutators, fewest moves first then lowest finger trick cost.
+*/
+pub fn find_commutators(
+    buffer: usize,
+    first: usize,
+    second: usize,
+    cost: &FingerCost,
+    max_results: usize,
+) -> Result<Vec<Commutator>, String> {
+    find_commutators_with(buffer, first, second, &all_moves(), cost, max_results)
+}
+
+// Same as `find_commutators` with only the given moves, e.g. face moves for FMC
+pub fn find_commutators_with(
+    buffer: usize,
+    first: usize,
+    second: usize,
+    allowed: &[Move],
+    cost: &FingerCost,
+    max_results: usize,
+) -> Result<Vec<Commutator>, String> {
+    if [buffer, first, second].iter().any(|&s| s >= Cube::CELL_COUNT) {
+        return Err("Sticker index out of range".to_string());
+    }
+    let pieces = [buffer, first, second].map(Cube::piece_of);
+    let same_kind = pieces.iter().all(|p| matches!(p, Piece::Edge(_)))
+        || pieces.iter().all(|p| matches!(p, Piece::Corner(_)));
+    if !same_kind {
+        return Err("The 3 stickers must all be on corners or all be on edges".to_string());
+    }
+    if pieces[0] == pieces[1] || pieces[1] == pieces[2] || pieces[0] == pieces[2] {
+        return Err("The 3 stickers must be on different pieces".to_string());
+    }
+
+    // buffer -> first -> second -> buffer, sticker by sticker
+    let cycle = [buffer, first, second].map(Cube::stickers_from);
+    let mut wanted = identity_perm();
+    for k in 0..cycle.len() {
+        for (&from, &to) in cycle[k].iter().zip(cycle[(k + 1) % cycle.len()].iter()) {
+            wanted[from] = to as u8;
+        }
+    }
+
+    let table = pure_commutators();
+    let moves = move_perms(allowed);
+    let is_allowed = |part: &[Move]| part.iter().all(|m| allowed.contains(m));
+    let mut found: Vec<Commutator> = Vec::new();
+    for depth in 0..=SETUP_MAX_DEPTH {
+        for_each_sequence(depth, &moves, &mut Vec::new(), &identity_perm(), &mut |setup, perm| {
+            // C X C' = wanted, so X = C' wanted C
+            let inner = compose(&compose(&invert(perm), &wanted), perm);
+            for (a, b) in table.get(&inner).into_iter().flatten().filter(|(a, b)| is_allowed(a) && is_allowed(b)) {
+                found.push(Commutator::new(setup.to_vec(), a.clone(), b.clone(), cost));
+            }
+        });
+        // Longer setups give longer commutators, stop at the first depth with a result
+        if !found.is_empty() {
+            break;
+        }
+    }
+
+    found.sort_by_key(|c| (c.move_count, c.cost));
+    let mut seen = Vec::new();
+    found.retain(|c| {
+        let moves = c.moves();
+        let new = !seen.contains(&moves);
+        seen.push(moves);
+        new
+    });
+    found.truncate(max_results);
+    Ok(found)
+}
@@ -1,3 +1,8 @@
+use crate::cubie::CubieCube;
+use crate::movetable::sticker_moves;
+use crate::notation::Move;
+use crate::render::Renderer;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Face {
     // The value of each face is the index of the first sticker in the state array
@@ -26,6 +31,64 @@ impl RotationDirection {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Slice {
+    // Inner layers, each one turns in the same direction as the face named in the comment
+    M, // Middle layer between L and R, follows L
+    E, // Equator layer between U and D, follows D
+    S, // Standing layer between F and B, follows F
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Axis {
+    UD, // U and D faces, E slice
+    RL, // R and L faces, M slice
+    FB, // F and B faces, S slice
+}
+
+impl Axis {
+    // Axis of a face given by its index in face order U, D, R, L, F, B
+    pub fn of_face(face: usize) -> Axis {
+        match face {
+            0 | 1 => Axis::UD,
+            2 | 3 => Axis::RL,
+            _ => Axis::FB,
+        }
+    }
+
+    // Priority of the faces when choosing the reference sticker of an edge for this axis.
+    // An edge is oriented when its highest priority sticker sits on the highest priority sticker of its slot
+    fn edge_priority(&self, face: usize) -> usize {
+        let axis_of = Axis::of_face;
+        let first = match self {
+            Axis::UD => Axis::FB,
+            _ => Axis::UD,
+        };
+        if axis_of(face) == first {
+            2
+        } else if axis_of(face) == *self {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Piece {
+    Center(usize), // Index into Cube::CENTERS
+    Edge(usize),   // Index into Cube::EDGE_FACELETS
+    Corner(usize), // Index into Cube::CORNER_FACELETS
+}
+
+// Stickers shown by `Cube::masked`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Mask {
+    Stickers(Vec<usize>), // Only these stickers
+    LastLayer,            // The U face and the top row of the side faces, for last layer training
+    Orientation(Axis),    // Only where the stickers of the axis faces are, for EO / CO practice
+}
+
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Copy)]
 pub struct Cube {
     /*  6 face and 9 stickers per face converted to 54 elements array
@@ -65,6 +128,42 @@ impl Cube {
         (45, 51, 53), (45, 53, 47), (51, 53, 47), (51, 47, 45), // Face B
     ];
 
+    // Index of the center sticker of each face, in face order U, D, R, L, F, B
+    pub const CENTERS: [usize; 6] = [4, 13, 22, 31, 40, 49];
+
+    // Stickers of the 8 corner pieces: URF, UFL, ULB, UBR, DFR, DLF, DBL, DRB
+    // The first sticker is always on U or D, the others follow clockwise around the corner
+    pub const CORNER_FACELETS: [[usize; 3]; 8] = [
+        [8, 18, 38],  // URF
+        [6, 36, 29],  // UFL
+        [0, 27, 47],  // ULB
+        [2, 45, 20],  // UBR
+        [11, 44, 24], // DFR
+        [9, 35, 42],  // DLF
+        [15, 53, 33], // DBL
+        [17, 26, 51], // DRB
+    ];
+
+    pub const CORNER_NAMES: [&'static str; 8] = ["URF", "UFL", "ULB", "UBR", "DFR", "DLF", "DBL", "DRB"];
+
+    // Stickers of the 12 edge pieces: UR, UF, UL, UB, DR, DF, DL, DB, FR, FL, BL, BR
+    pub const EDGE_FACELETS: [[usize; 2]; 12] = [
+        [5, 19],  // UR
+        [7, 37],  // UF
+        [3, 28],  // UL
+        [1, 46],  // UB
+        [14, 25], // DR
+        [10, 43], // DF
+        [12, 34], // DL
+        [16, 52], // DB
+        [41, 21], // FR
+        [39, 32], // FL
+        [50, 30], // BL
+        [48, 23], // BR
+    ];
+
+    pub const EDGE_NAMES: [&'static str; 12] = ["UR", "UF", "UL", "UB", "DR", "DF", "DL", "DB", "FR", "FL", "BL", "BR"];
+
     pub const MOVES: [(Face, RotationDirection); 18] = [
         (Face::U, RotationDirection::Clockwise),
         (Face::U, RotationDirection::CounterClockwise),
@@ -102,6 +201,13 @@ impl Cube {
     Ok(Self { state: cube_state })
 }
 
+    // Same as `new` with stickers, which must pass `validate`
+    pub fn checked(state: &[u8]) -> Result<Self, String> {
+        let cube = Self::new(Some(state))?;
+        cube.validate()?;
+        Ok(cube)
+    }
+
     // Hàm trả về trạng thái đã giải của Rubik
     fn default_state() -> [u8; Self::CELL_COUNT] {
         // Rubik is an array of 54 elements
@@ -113,18 +219,151 @@ impl Cube {
     }
 
 
-    // Print state of the cube
+    // Print the cube as a colored net (see `Renderer`)
     pub fn print(&self) {
-        let faces = ["U", "D", "R", "L", "F", "B"];
-        for (i, face) in faces.iter().enumerate() {
-            println!("{} Face:", face);
-            for row in 0..Self::EDGE_SIZE {
-                println!("{:?}", &self.state[i * Self::FACE_SIZE
-                    + row * Self::EDGE_SIZE..i * Self::FACE_SIZE
-                    + (row + 1) * Self::EDGE_SIZE]);
+        print!("{}", Renderer::default().render(self));
+    }
+
+    // Letter of each color in facelet strings: the face that has it in the solved cube
+    pub const FACELET_LETTERS: [char; 6] = ['U', 'D', 'R', 'L', 'F', 'B'];
+
+    /**
+    Cube from a facelet string: 54 letters in state order (U, D, R, L, F and B faces), each one
+    names the face whose color the sticker has in the solved cube, '?' for an unknown sticker.
+    - The cube is validated like with `Cube::new`.
+    */
+    pub fn from_facelets(text: &str) -> Result<Cube, String> {
+        let state: Vec<u8> = text.trim().chars().map(|c| match c {
+            '?' => Ok(Self::UNKNOWN),
+            c => Self::FACELET_LETTERS.iter()
+                .position(|&letter| letter == c.to_ascii_uppercase())
+                .map(|color| color as u8)
+                .ok_or_else(|| format!("Invalid facelet: {}", c)),
+        }).collect::<Result<_, _>>()?;
+        Cube::checked(&state)
+    }
+
+    pub fn facelets(&self) -> String {
+        self.state.iter().map(|&color| Self::FACELET_LETTERS.get(color as usize).copied().unwrap_or('?')).collect()
+    }
+
+    // Sticker value for an unknown or masked sticker, it matches any color
+    pub const UNKNOWN: u8 = u8::MAX;
+    pub const COLOR_COUNT: usize = 6;
+
+    /**
+    Check that the stickers can come from a real cube.
+    - Colors are 0..5 or UNKNOWN, no color appears more than 9 times (exactly 9 times without unknown stickers).
+    - Known centers have different colors, and no piece shows the same color twice or the colors of 2 opposite centers.
+    - Without unknown stickers, every piece must be found exactly once.
+    */
+    pub fn validate(&self) -> Result<(), String> {
+        let mut counts = [0; Self::COLOR_COUNT];
+        for &color in self.state.iter() {
+            match color {
+                Self::UNKNOWN => {}
+                c if (c as usize) < Self::COLOR_COUNT => counts[c as usize] += 1,
+                c => return Err(format!("Invalid color: {}", c)),
+            }
+        }
+        let masked = self.is_masked();
+        for (color, &count) in counts.iter().enumerate() {
+            if count > Self::FACE_SIZE || (!masked && count != Self::FACE_SIZE) {
+                return Err(format!("Color {} appears {} times", color, count));
+            }
+        }
+
+        let centers: Vec<u8> = Self::CENTERS.iter().map(|&i| self.state[i]).collect();
+        for (i, &a) in centers.iter().enumerate() {
+            if a != Self::UNKNOWN && centers[i + 1..].contains(&a) {
+                return Err(format!("Two centers have color {}", a));
+            }
+        }
+        // Opposite faces come in pairs in face order: U/D, R/L, F/B
+        let opposite = |a: u8, b: u8| {
+            centers.chunks(2).any(|pair| !pair.contains(&Self::UNKNOWN) && pair.contains(&a) && pair.contains(&b))
+        };
+        let pieces = Self::EDGE_FACELETS.iter().map(|e| e.to_vec()).chain(Self::CORNER_FACELETS.iter().map(|c| c.to_vec()));
+        for piece in pieces {
+            let colors: Vec<u8> = piece.iter().map(|&i| self.state[i]).filter(|&c| c != Self::UNKNOWN).collect();
+            for (i, &a) in colors.iter().enumerate() {
+                if colors[i + 1..].iter().any(|&b| a == b || opposite(a, b)) {
+                    return Err(format!("Impossible piece at stickers {:?}", piece));
+                }
+            }
+        }
+
+        if !masked {
+            let labelled = self.labelled().ok_or("Pieces can't be identified")?;
+            if (0..Self::CELL_COUNT).any(|i| !labelled.state.contains(&(i as u8))) {
+                return Err("A piece appears twice".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    // True when some stickers are unknown
+    pub fn is_masked(&self) -> bool {
+        self.state.contains(&Self::UNKNOWN)
+    }
+
+    // Same stickers, an unknown sticker on either cube matches anything
+    pub fn matches(&self, other: &Cube) -> bool {
+        self.state.iter().zip(other.state.iter())
+            .all(|(&a, &b)| a == b || a == Self::UNKNOWN || b == Self::UNKNOWN)
+    }
+
+    // Solved for the known stickers: each one has the color of its center (or of the other known
+    // stickers of its face when the center is unknown)
+    pub fn is_solved_masked(&self) -> bool {
+        self.state.chunks(Self::FACE_SIZE).all(|face| {
+            let mut known = face.iter().filter(|&&c| c != Self::UNKNOWN);
+            let color = match face[Self::FACE_SIZE / 2] {
+                Self::UNKNOWN => known.next().copied(),
+                center => Some(center),
+            };
+            known.all(|&c| Some(c) == color)
+        })
+    }
+
+    /**
+    Copy of the cube that only shows part of the stickers, the others are UNKNOWN.
+    - The centers are always kept, they tell the color of each face.
+    - With `Mask::Orientation`, the stickers of the 2 faces of the axis all show the color of
+      the first one, e.g. U and D stickers are all shown with the U color. Such a cube only makes
+      sense with `is_solved_masked` and the solvers, `validate` rejects it.
+    */
+    pub fn masked(&self, mask: &Mask) -> Cube {
+        let mut state = [Self::UNKNOWN; Self::CELL_COUNT];
+        for &center in Self::CENTERS.iter() {
+            state[center] = self.state[center];
+        }
+        match mask {
+            Mask::Stickers(stickers) => {
+                for &i in stickers {
+                    state[i] = self.state[i];
+                }
+            }
+            Mask::LastLayer => {
+                // U face and the top row of the side faces
+                let top_rows = [Face::R, Face::L, Face::F, Face::B].into_iter()
+                    .flat_map(|face| face as usize..face as usize + Self::EDGE_SIZE);
+                for i in (0..Self::FACE_SIZE).chain(top_rows) {
+                    state[i] = self.state[i];
+                }
+            }
+            Mask::Orientation(axis) => {
+                let faces: Vec<usize> = (0..Self::CENTERS.len()).filter(|&f| Axis::of_face(f) == *axis).collect();
+                let shown = self.state[Self::CENTERS[faces[0]]];
+                let hidden = self.state[Self::CENTERS[faces[1]]];
+                for (i, &color) in self.state.iter().enumerate() {
+                    if color == shown || color == hidden {
+                        state[i] = shown;
+                    }
+                }
             }
-            println!();
         }
+        Cube { state }
     }
 
     pub fn is_solved(&self) -> bool {
@@ -196,9 +435,14 @@ impl Cube {
         - 2 5 8: Column is right of face -> Last element of each row is right
         */
 
-        let indices = match face {
-            // Define the indices of the stickers in the adjacent faces
-            // Order of adjacent faces in array turn clockwise
+        let indices = Self::adjacent_indices(face);
+        self.cycle_strips(&indices);
+    }
+
+    // Indices of the 4 strips moved by a face turn.
+    // Stickers travel from strip 0 to strip 1, 1 to 2, 2 to 3 and 3 back to 0
+    fn adjacent_indices(face: Face) -> [[usize; 3]; 4] {
+        match face {
             Face::U => [
                 [36, 37, 38], // F.top
                 [27, 28, 29], // L.top
@@ -213,30 +457,60 @@ impl Cube {
             ],
             Face::R => [
                 [38, 41, 44], // F.right
-                [20, 23, 26], // U.right
-                [47, 50, 53], // B.left
-                [9, 12, 15],  // D.right
+                [2, 5, 8],    // U.right
+                [51, 48, 45], // B.left (bottom to top)
+                [11, 14, 17], // D.right
             ],
             Face::L => [
-                [36, 39, 42], // F.left
-                [11, 14, 17], // D.left
-                [45, 48, 51], // B.right
                 [0, 3, 6],    // U.left
+                [36, 39, 42], // F.left
+                [9, 12, 15],  // D.left
+                [53, 50, 47], // B.right (bottom to top)
             ],
             Face::F => [
                 [6, 7, 8],    // U.bottom
-                [27, 30, 33], // L.right
-                [15, 16, 17], // D.top
                 [18, 21, 24], // R.left
+                [11, 10, 9],  // D.top (right to left)
+                [35, 32, 29], // L.right (bottom to top)
             ],
             Face::B => [
-                [0, 1, 2],    // U.top
-                [9, 10, 11],  // D.bottom
-                [29, 32, 35], // L.left
-                [51, 48, 45], // R.right
+                [2, 1, 0],    // U.top (right to left)
+                [27, 30, 33], // L.left
+                [15, 16, 17], // D.bottom
+                [26, 23, 20], // R.right (bottom to top)
             ],
-        };
+        }
+    }
+
+    // Indices of the 4 strips moved by a slice turn, same convention as adjacent_indices
+    fn slice_indices(slice: Slice) -> [[usize; 3]; 4] {
+        match slice {
+            // M follows L: U -> F -> D -> B
+            Slice::M => [
+                [1, 4, 7],    // U.middle column
+                [37, 40, 43], // F.middle column
+                [10, 13, 16], // D.middle column
+                [52, 49, 46], // B.middle column (bottom to top)
+            ],
+            // E follows D: F -> R -> B -> L
+            Slice::E => [
+                [39, 40, 41], // F.middle row
+                [21, 22, 23], // R.middle row
+                [48, 49, 50], // B.middle row
+                [30, 31, 32], // L.middle row
+            ],
+            // S follows F: U -> R -> D -> L
+            Slice::S => [
+                [3, 4, 5],    // U.middle row
+                [19, 22, 25], // R.middle column
+                [14, 13, 12], // D.middle row (right to left)
+                [34, 31, 28], // L.middle column (bottom to top)
+            ],
+        }
+    }
 
+    // Move the stickers of 4 strips one step along the cycle 0 -> 1 -> 2 -> 3 -> 0
+    fn cycle_strips(&mut self, indices: &[[usize; 3]; 4]) {
         let temp = [self.state[indices[0][0]], self.state[indices[0][1]], self.state[indices[0][2]]];
 
         for i in 0..Self::EDGE_SIZE {
@@ -251,6 +525,11 @@ impl Cube {
     // face: Face need to rotate
     // direction: diriction rotate (Clockwise, CounterClockwise, DoubleTurn)
     pub fn rotate(&mut self, face: Face, direction: RotationDirection) {
+        self.gather(&sticker_moves()[Move::face(face, direction).index()]);
+    }
+
+    // Same turn with the index math of each quarter turn, also used to build the move tables
+    pub fn rotate_strips(&mut self, face: Face, direction: RotationDirection) {
         /* Example:
         * Before rotate:          After rotate:
                 0 1 2                  2 1 0
@@ -276,9 +555,163 @@ impl Cube {
         }
     }
 
+    // Sticker i takes the sticker at `from[i]`
+    pub fn gather(&mut self, from: &[u8; Self::CELL_COUNT]) {
+        // A u8 index into 256 stickers needs no bounds check
+        let mut state = [0; 256];
+        state[..Self::CELL_COUNT].copy_from_slice(&self.state);
+        for (sticker, &from) in self.state.iter_mut().zip(from) {
+            *sticker = state[from as usize];
+        }
+    }
+
     pub fn apply_move(&self, face: Face, direction: RotationDirection) -> Cube {
-        let mut new_cube = self.clone(); // Make a copy of the cube
+        let mut new_cube = *self; // Make a copy of the cube
         new_cube.rotate(face, direction); // Rotate the face
         new_cube
     }
+
+    // Function to rotate an inner slice (M, E, S)
+    // Only the 4 strips of the slice move, the centers of U/D/R/L/F/B in the slice move with it
+    pub fn rotate_slice(&mut self, slice: Slice, direction: RotationDirection) {
+        self.gather(&sticker_moves()[Move::slice(slice, direction).index()]);
+    }
+
+    // Same turn with the index math of each quarter turn, also used to build the move tables
+    pub fn rotate_slice_strips(&mut self, slice: Slice, direction: RotationDirection) {
+        let indices = Self::slice_indices(slice);
+        for _ in 0..direction as usize {
+            self.cycle_strips(&indices);
+        }
+    }
+
+    /**
+    Replace every sticker by the index of its home position (0..53).
+    - The home face of a color is the face whose center currently has that color.
+    - A labelled cube is still a valid `Cube`, so it can be rotated as usual, and the sticker
+      that belongs at position i is solved exactly when `state[i] == i`.
+    - Returns None when a piece can't be identified from its colors.
+    */
+    pub fn labelled(&self) -> Option<Cube> {
+        let mut home_face = [usize::MAX; 256];
+        for (face, &center) in Self::CENTERS.iter().enumerate() {
+            if home_face[self.state[center] as usize] != usize::MAX {
+                return None; // Two centers with the same color
+            }
+            home_face[self.state[center] as usize] = face;
+        }
+        let face_of = |idx: usize| home_face[self.state[idx] as usize];
+
+        let mut labels = [0u8; Self::CELL_COUNT];
+        for &center in Self::CENTERS.iter() {
+            labels[center] = Self::CENTERS[face_of(center)] as u8;
+        }
+
+        for slot in Self::CORNER_FACELETS.iter() {
+            let seen = [face_of(slot[0]), face_of(slot[1]), face_of(slot[2])];
+            let (piece, twist) = Self::CORNER_FACELETS.iter()
+                .flat_map(|piece| (0..3).map(move |twist| (piece, twist)))
+                .find(|(piece, twist)| (0..3).all(|k| seen[k] == piece[(k + twist) % 3] / Self::FACE_SIZE))?;
+            for k in 0..3 {
+                labels[slot[k]] = piece[(k + twist) % 3] as u8;
+            }
+        }
+
+        for slot in Self::EDGE_FACELETS.iter() {
+            let seen = [face_of(slot[0]), face_of(slot[1])];
+            let (piece, flip) = Self::EDGE_FACELETS.iter()
+                .flat_map(|piece| (0..2).map(move |flip| (piece, flip)))
+                .find(|(piece, flip)| (0..2).all(|k| seen[k] == piece[(k + flip) % 2] / Self::FACE_SIZE))?;
+            for k in 0..2 {
+                labels[slot[k]] = piece[(k + flip) % 2] as u8;
+            }
+        }
+
+        Some(Cube { state: labels })
+    }
+
+    /**
+    Whether moves can solve the cube: its pieces can be identified, the corner twists add up to a
+    multiple of 3, the edge flips to a multiple of 2, and the corner and edge permutations have
+    the same parity.
+    */
+    pub fn is_solvable(&self) -> bool {
+        CubieCube::from_cube(self).is_some_and(|cubie| cubie.is_solvable())
+    }
+
+    /**
+    Orientation of the 12 edges (order of EDGE_FACELETS) relative to an axis.
+    - `true` means the edge is flipped: it can't be solved without quarter turns of the axis faces.
+    - With Axis::FB, F and B quarter turns flip 4 edges and every other move keeps the orientation.
+    - Returns None when the pieces can't be identified.
+    */
+    pub fn flipped_edges(&self, axis: Axis) -> Option<[bool; 12]> {
+        Some(Self::labelled_flipped_edges(&self.labelled()?, axis))
+    }
+
+    // Same as flipped_edges, for a cube that is already labelled
+    pub fn labelled_flipped_edges(labelled: &Cube, axis: Axis) -> [bool; 12] {
+        let reference = |edge: &[usize; 2]| {
+            if axis.edge_priority(edge[0] / Self::FACE_SIZE) >= axis.edge_priority(edge[1] / Self::FACE_SIZE) {
+                edge[0]
+            } else {
+                edge[1]
+            }
+        };
+
+        let mut flipped = [false; 12];
+        for (slot, facelets) in Self::EDGE_FACELETS.iter().enumerate() {
+            let label = labelled.state[reference(facelets)] as usize;
+            let piece = Self::EDGE_FACELETS.iter()
+                .find(|piece| piece.contains(&label))
+                .expect("Sticker of an edge slot must belong to an edge");
+            flipped[slot] = label != reference(piece);
+        }
+        flipped
+    }
+
+    // Piece that owns a sticker position
+    pub fn piece_of(sticker: usize) -> Piece {
+        if let Some(center) = Self::CENTERS.iter().position(|&c| c == sticker) {
+            return Piece::Center(center);
+        }
+        if let Some(edge) = Self::EDGE_FACELETS.iter().position(|e| e.contains(&sticker)) {
+            return Piece::Edge(edge);
+        }
+        let corner = Self::CORNER_FACELETS.iter().position(|c| c.contains(&sticker))
+            .expect("Every sticker belongs to a piece");
+        Piece::Corner(corner)
+    }
+
+    // Stickers of a piece, in the order of CENTERS / EDGE_FACELETS / CORNER_FACELETS
+    pub fn piece_stickers(piece: Piece) -> Vec<usize> {
+        match piece {
+            Piece::Center(i) => vec![Self::CENTERS[i]],
+            Piece::Edge(i) => Self::EDGE_FACELETS[i].to_vec(),
+            Piece::Corner(i) => Self::CORNER_FACELETS[i].to_vec(),
+        }
+    }
+
+    pub fn piece_name(piece: Piece) -> &'static str {
+        const CENTER_NAMES: [&str; 6] = ["U", "D", "R", "L", "F", "B"];
+        match piece {
+            Piece::Center(i) => CENTER_NAMES[i],
+            Piece::Edge(i) => Self::EDGE_NAMES[i],
+            Piece::Corner(i) => Self::CORNER_NAMES[i],
+        }
+    }
+
+    // Stickers of the piece that owns `sticker`, starting at `sticker` and in the piece order after it.
+    // Two pieces listed this way line up sticker by sticker when one is moved onto the other
+    pub fn stickers_from(sticker: usize) -> Vec<usize> {
+        let stickers = Self::piece_stickers(Self::piece_of(sticker));
+        let start = stickers.iter().position(|&s| s == sticker).unwrap();
+        (0..stickers.len()).map(|k| stickers[(start + k) % stickers.len()]).collect()
+    }
+
+    // Current position of the sticker that belongs at `home`, for a labelled cube
+    pub fn locate(&self, home: usize) -> usize {
+        self.state.iter().position(|&label| label as usize == home)
+            .expect("Every label appears once in a labelled cube")
+    }
 }
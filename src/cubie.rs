@@ -0,0 +1,342 @@
+/*!
+* Cubie level state and its perfect ranking
+* - A `CubieCube` says which corner and edge is in each slot and how it is turned. Pieces and
+*   slots are numbered as `Cube::CORNER_FACELETS` and `Cube::EDGE_FACELETS`. The orientation of
+*   a piece is which of its stickers lies on the first sticker of the slot: 0 when its U/D sticker
+*   is on U/D (F/B for the E slice edges).
+* - Centers are left out: pieces are seen relative to the centers (see `Cube::labelled`).
+* - `rank` numbers the 8! * 3^7 * 12!/2 * 2^11 = 43 252 003 274 489 856 000 solvable states from
+*   0 and `unrank` is its inverse. That is more than a u64 holds (about 1.8e19), so the rank is a
+*   u128, stored in 9 bytes by `to_bytes`.
+* - `PieceSubset` ranks the slots and orientations of some pieces only, into a u64: the index
+*   of a pattern database.
+*/
+
+use crate::cube::Cube;
+
+pub const CORNER_COUNT: usize = 8;
+pub const EDGE_COUNT: usize = 12;
+pub const TWIST_COUNT: u128 = 2187; // 3^7, the twist of the last corner follows from the others
+pub const FLIP_COUNT: u128 = 2048; // 2^11
+pub const CORNER_PERM_COUNT: u128 = 40320; // 8!
+const EDGE_PERM_COUNT: u128 = 479001600 / 2; // 12!, half of them with the parity of the corners
+const RANK_BYTES: usize = 9;
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct CubieCube {
+    pub corners: [u8; CORNER_COUNT], // Piece in each slot
+    pub twists: [u8; CORNER_COUNT], // 0 to 2
+    pub edges: [u8; EDGE_COUNT],
+    pub flips: [u8; EDGE_COUNT], // 0 or 1
+}
+
+// Lehmer code of a permutation of 0..n, in 0..n!
+pub fn perm_rank(perm: &[u8]) -> u64 {
+    partial_perm_rank(perm, perm.len())
+}
+
+// Permutation of 0..n with the given Lehmer code
+pub fn perm_unrank(n: usize, rank: u64) -> Vec<u8> {
+    partial_perm_unrank(n, n, rank)
+}
+
+// Number of ways to place k distinct pieces in n slots: n! / (n - k)!
+pub fn placements(n: usize, k: usize) -> u64 {
+    (n - k + 1..=n).map(|i| i as u64).product()
+}
+
+/**
+Rank of k distinct values of 0..n in order (the slots of k pieces), in 0..placements(n, k).
+- Each value counts as its position among the values not used before it.
+*/
+pub fn partial_perm_rank(values: &[u8], n: usize) -> u64 {
+    let mut used = vec![false; n];
+    let mut rank = 0;
+    for (i, &value) in values.iter().enumerate() {
+        let smaller_free = used[..value as usize].iter().filter(|&&u| !u).count() as u64;
+        rank = rank * (n - i) as u64 + smaller_free;
+        used[value as usize] = true;
+    }
+    rank
+}
+
+// The k values of 0..n with the given rank, inverse of `partial_perm_rank`
+pub fn partial_perm_unrank(n: usize, k: usize, mut rank: u64) -> Vec<u8> {
+    let mut digits = vec![0; k];
+    for i in (0..k).rev() {
+        let base = (n - i) as u64;
+        digits[i] = (rank % base) as usize;
+        rank /= base;
+    }
+    let mut free: Vec<u8> = (0..n as u8).collect();
+    digits.into_iter().map(|digit| free.remove(digit)).collect()
+}
+
+// Parity of a permutation: odd when it has an odd number of even length cycles
+pub fn is_odd(perm: &[u8]) -> bool {
+    let mut seen = vec![false; perm.len()];
+    let mut cycles = 0;
+    for start in 0..perm.len() {
+        if !seen[start] {
+            cycles += 1;
+            let mut i = start;
+            while !seen[i] {
+                seen[i] = true;
+                i = perm[i] as usize;
+            }
+        }
+    }
+    (perm.len() - cycles) % 2 == 1
+}
+
+impl Default for CubieCube {
+    fn default() -> Self {
+        Self::solved()
+    }
+}
+
+impl CubieCube {
+    pub const STATE_COUNT: u128 = CORNER_PERM_COUNT * TWIST_COUNT * EDGE_PERM_COUNT * FLIP_COUNT;
+
+    pub fn solved() -> Self {
+        CubieCube {
+            corners: std::array::from_fn(|i| i as u8),
+            twists: [0; CORNER_COUNT],
+            edges: std::array::from_fn(|i| i as u8),
+            flips: [0; EDGE_COUNT],
+        }
+    }
+
+    // Pieces of a sticker cube, None when they can't be identified from their colors
+    pub fn from_cube(cube: &Cube) -> Option<Self> {
+        let labelled = cube.labelled()?;
+        // Home piece of each slot, and which of its stickers is on the first sticker of the slot
+        let pieces = |slots: &[&[usize]], homes: &mut [u8], orientations: &mut [u8]| {
+            for (slot, facelets) in slots.iter().enumerate() {
+                let label = labelled.state[facelets[0]] as usize;
+                let (home, k) = slots.iter().enumerate()
+                    .find_map(|(home, piece)| piece.iter().position(|&s| s == label).map(|k| (home, k)))
+                    .expect("Labelled pieces are pieces");
+                homes[slot] = home as u8;
+                orientations[slot] = k as u8;
+            }
+        };
+        let corners: Vec<&[usize]> = Cube::CORNER_FACELETS.iter().map(|c| c.as_slice()).collect();
+        let edges: Vec<&[usize]> = Cube::EDGE_FACELETS.iter().map(|e| e.as_slice()).collect();
+        let mut cubie = CubieCube::solved();
+        pieces(&corners, &mut cubie.corners, &mut cubie.twists);
+        pieces(&edges, &mut cubie.edges, &mut cubie.flips);
+        Some(cubie)
+    }
+
+    // The sticker cube, centers at home
+    pub fn to_cube(&self) -> Cube {
+        let mut cube = Cube::new(None).expect("Error initializing cube");
+        let mut place = |slot: &[usize], piece: &[usize], orientation: u8| {
+            for (k, &sticker) in slot.iter().enumerate() {
+                cube.state[sticker] = (piece[(k + orientation as usize) % piece.len()] / Cube::FACE_SIZE) as u8;
+            }
+        };
+        for slot in 0..CORNER_COUNT {
+            place(&Cube::CORNER_FACELETS[slot], &Cube::CORNER_FACELETS[self.corners[slot] as usize], self.twists[slot]);
+        }
+        for slot in 0..EDGE_COUNT {
+            place(&Cube::EDGE_FACELETS[slot], &Cube::EDGE_FACELETS[self.edges[slot] as usize], self.flips[slot]);
+        }
+        cube
+    }
+
+    /**
+    Whether moves can solve it: every piece once, corner twists adding up to a multiple of 3,
+    edge flips to a multiple of 2, and corner and edge permutations of the same parity.
+    */
+    pub fn is_solvable(&self) -> bool {
+        let is_perm = |pieces: &[u8]| (0..pieces.len() as u8).all(|piece| pieces.contains(&piece));
+        is_perm(&self.corners) && is_perm(&self.edges)
+            && self.twists.iter().all(|&t| t < 3) && self.flips.iter().all(|&f| f < 2)
+            && self.twists.iter().map(|&t| t as usize).sum::<usize>() % 3 == 0
+            && self.flips.iter().map(|&f| f as usize).sum::<usize>() % 2 == 0
+            && is_odd(&self.corners) == is_odd(&self.edges)
+    }
+
+    /**
+    Apply `other` after this state, e.g. a move from `movetable::cubie_move`: each slot gets the
+    piece of the slot `other` takes it from, with both twists (or flips) added.
+    */
+    pub fn multiply(&self, other: &CubieCube) -> CubieCube {
+        CubieCube {
+            corners: std::array::from_fn(|slot| self.corners[other.corners[slot] as usize]),
+            twists: std::array::from_fn(|slot| (self.twists[other.corners[slot] as usize] + other.twists[slot]) % 3),
+            edges: std::array::from_fn(|slot| self.edges[other.edges[slot] as usize]),
+            flips: std::array::from_fn(|slot| (self.flips[other.edges[slot] as usize] + other.flips[slot]) % 2),
+        }
+    }
+
+    // Twists of the first 7 corners in base 3, 0 to 2186
+    pub fn twist(&self) -> u16 {
+        self.twists[..CORNER_COUNT - 1].iter().fold(0, |acc, &t| acc * 3 + t as u16)
+    }
+
+    // Set the twists, the last corner makes their sum a multiple of 3
+    pub fn set_twist(&mut self, mut twist: u16) {
+        for slot in (0..CORNER_COUNT - 1).rev() {
+            self.twists[slot] = (twist % 3) as u8;
+            twist /= 3;
+        }
+        let sum: u8 = self.twists[..CORNER_COUNT - 1].iter().sum();
+        self.twists[CORNER_COUNT - 1] = (3 - sum % 3) % 3;
+    }
+
+    // Flips of the first 11 edges in base 2, 0 to 2047
+    pub fn flip(&self) -> u16 {
+        self.flips[..EDGE_COUNT - 1].iter().fold(0, |acc, &f| acc * 2 + f as u16)
+    }
+
+    // Set the flips, the last edge makes their sum even
+    pub fn set_flip(&mut self, mut flip: u16) {
+        for slot in (0..EDGE_COUNT - 1).rev() {
+            self.flips[slot] = (flip % 2) as u8;
+            flip /= 2;
+        }
+        self.flips[EDGE_COUNT - 1] = self.flips[..EDGE_COUNT - 1].iter().sum::<u8>() % 2;
+    }
+
+    // Lehmer code of the corners, 0 to 40319
+    pub fn corner_perm(&self) -> u16 {
+        perm_rank(&self.corners) as u16
+    }
+
+    pub fn set_corner_perm(&mut self, rank: u16) {
+        self.corners.copy_from_slice(&perm_unrank(CORNER_COUNT, rank as u64));
+    }
+
+    // Index in 0..STATE_COUNT, None when the state isn't solvable
+    pub fn rank(&self) -> Option<u128> {
+        if !self.is_solvable() {
+            return None;
+        }
+        // The Lehmer codes 2k and 2k+1 only differ by a swap of the last 2 edges, so one parity each
+        let edges = perm_rank(&self.edges) as u128 / 2;
+        let corners = self.corner_perm() as u128 * TWIST_COUNT + self.twist() as u128;
+        Some(((corners * EDGE_PERM_COUNT) + edges) * FLIP_COUNT + self.flip() as u128)
+    }
+
+    // The state with that index, None past STATE_COUNT
+    pub fn unrank(rank: u128) -> Option<Self> {
+        if rank >= Self::STATE_COUNT {
+            return None;
+        }
+        let (rest, flip) = (rank / FLIP_COUNT, rank % FLIP_COUNT);
+        let (rest, edges) = (rest / EDGE_PERM_COUNT, rest % EDGE_PERM_COUNT);
+        let (corners, twist) = (rest / TWIST_COUNT, rest % TWIST_COUNT);
+
+        let mut cubie = CubieCube::solved();
+        cubie.set_corner_perm(corners as u16);
+        cubie.set_twist(twist as u16);
+        cubie.set_flip(flip as u16);
+        cubie.edges.copy_from_slice(&perm_unrank(EDGE_COUNT, edges as u64 * 2));
+        if is_odd(&cubie.edges) != is_odd(&cubie.corners) {
+            cubie.edges.swap(EDGE_COUNT - 2, EDGE_COUNT - 1);
+        }
+        Some(cubie)
+    }
+
+    // The rank in 9 bytes, big endian; None when the state isn't solvable
+    pub fn to_bytes(&self) -> Option<[u8; RANK_BYTES]> {
+        let bytes = self.rank()?.to_be_bytes();
+        Some(bytes[bytes.len() - RANK_BYTES..].try_into().expect("9 bytes"))
+    }
+
+    pub fn from_bytes(bytes: &[u8; RANK_BYTES]) -> Option<Self> {
+        let mut full = [0; 16];
+        full[16 - RANK_BYTES..].copy_from_slice(bytes);
+        Self::unrank(u128::from_be_bytes(full))
+    }
+}
+
+/**
+Some corners and edges, ranked by their slots and orientations only.
+- k of n pieces have n!/(n-k)! placements and 3^k or 2^k orientations (every orientation
+  counts, even when all the pieces are tracked).
+- Corners come first: rank = corner rank * edge count + edge rank.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PieceSubset {
+    pub corners: Vec<u8>,
+    pub edges: Vec<u8>,
+    corner_count: u64,
+    edge_count: u64,
+}
+
+impl PieceSubset {
+    // Error when a piece is given twice or doesn't exist, or the ranks don't fit in a u64
+    pub fn new(corners: &[u8], edges: &[u8]) -> Result<Self, String> {
+        let check = |pieces: &[u8], n: usize, name: &str| -> Result<u64, String> {
+            for (i, &piece) in pieces.iter().enumerate() {
+                if piece as usize >= n || pieces[..i].contains(&piece) {
+                    return Err(format!("Invalid {} list: {:?}", name, pieces));
+                }
+            }
+            let orientations = if n == CORNER_COUNT { 3u64 } else { 2 };
+            orientations.checked_pow(pieces.len() as u32)
+                .and_then(|o| o.checked_mul(placements(n, pieces.len())))
+                .ok_or_else(|| "Too many pieces for a u64 rank".to_string())
+        };
+        let corner_count = check(corners, CORNER_COUNT, "corner")?;
+        let edge_count = check(edges, EDGE_COUNT, "edge")?;
+        corner_count.checked_mul(edge_count).ok_or("Too many pieces for a u64 rank")?;
+        Ok(PieceSubset { corners: corners.to_vec(), edges: edges.to_vec(), corner_count, edge_count })
+    }
+
+    // Ranks go from 0 to count() - 1
+    pub fn count(&self) -> u64 {
+        self.corner_count * self.edge_count
+    }
+
+    pub fn rank(&self, cubie: &CubieCube) -> u64 {
+        // Slot and orientation of each tracked piece
+        let part = |pieces: &[u8], slots: &[u8], orientations: &[u8], base: u64| {
+            let places: Vec<u8> = pieces.iter()
+                .map(|&piece| slots.iter().position(|&p| p == piece).expect("Every piece has a slot") as u8)
+                .collect();
+            let orientation = places.iter().fold(0, |acc, &slot| acc * base + orientations[slot as usize] as u64);
+            partial_perm_rank(&places, slots.len()) * base.pow(pieces.len() as u32) + orientation
+        };
+        let corners = part(&self.corners, &cubie.corners, &cubie.twists, 3);
+        let edges = part(&self.edges, &cubie.edges, &cubie.flips, 2);
+        corners * self.edge_count + edges
+    }
+
+    /**
+    A state with that rank, None past count(). The other pieces fill the free slots in order,
+    unturned, so the state may not be solvable.
+    */
+    pub fn unrank(&self, rank: u64) -> Option<CubieCube> {
+        if rank >= self.count() {
+            return None;
+        }
+        let part = |pieces: &[u8], rank: u64, slots: &mut [u8], orientations: &mut [u8], base: u64| {
+            let combinations = base.pow(pieces.len() as u32);
+            let places = partial_perm_unrank(slots.len(), pieces.len(), rank / combinations);
+            let mut orientation = rank % combinations;
+            let mut others = (0..slots.len() as u8).filter(|piece| !pieces.contains(piece));
+            let mut filled = vec![false; slots.len()];
+            for (i, &slot) in places.iter().enumerate().rev() {
+                slots[slot as usize] = pieces[i];
+                orientations[slot as usize] = (orientation % base) as u8;
+                orientation /= base;
+                filled[slot as usize] = true;
+            }
+            for slot in 0..slots.len() {
+                if !filled[slot] {
+                    slots[slot] = others.next().expect("As many pieces as slots");
+                    orientations[slot] = 0;
+                }
+            }
+        };
+        let mut cubie = CubieCube::solved();
+        part(&self.corners, rank / self.edge_count, &mut cubie.corners, &mut cubie.twists, 3);
+        part(&self.edges, rank % self.edge_count, &mut cubie.edges, &mut cubie.flips, 2);
+        Some(cubie)
+    }
+}
@@ -0,0 +1,247 @@
+/*!
+* C API, for linking the solver into C and C++ programs (see include/rubik.h)
+* - A cube is an opaque `RubikCube *`, made by `rubik_cube_new` or `rubik_cube_from_facelets`
+*   and released by `rubik_cube_free`.
+* - Functions that can fail return a `RubikStatus`. When `error` isn't NULL, it receives a
+*   message for any status but RUBIK_OK.
+* - Every string given to the caller (messages, solutions, facelets) is released by
+*   `rubik_string_free`.
+* - Strings from the caller are NUL terminated UTF-8.
+* - No panic crosses the boundary: a panic gives RUBIK_PANIC (or NULL) and the library can
+*   still be used.
+*/
+
+use crate::cube::Cube;
+use crate::notation::{apply_moves, format_moves, parse_moves};
+use crate::solver::{solve_timed, SolveError, SolveMethod};
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::time::Duration;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RubikStatus {
+    Ok = 0,
+    NullArgument = 1,
+    InvalidUtf8 = 2,
+    InvalidFacelets = 3,
+    InvalidMoves = 4,
+    InvalidMethod = 5,
+    Unsolvable = 6,
+    NoSolution = 7,
+    Timeout = 8,
+    Panic = 9,
+}
+
+type Failure = (RubikStatus, String);
+
+// A string for the caller, NUL bytes (never in our messages) are dropped
+fn to_c_string(text: &str) -> *mut c_char {
+    CString::new(text.replace('\0', "")).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/**
+Run `body`, turning its failure or its panic into a status and a message in `error`.
+# Safety
+`error` must be NULL or valid for writes.
+*/
+unsafe fn guard(error: *mut *mut c_char, body: impl FnOnce() -> Result<(), Failure>) -> RubikStatus {
+    let (status, message) = match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => return RubikStatus::Ok,
+        Ok(Err(failure)) => failure,
+        Err(_) => (RubikStatus::Panic, "Internal error in the solver".to_string()),
+    };
+    if !error.is_null() {
+        *error = to_c_string(&message);
+    }
+    status
+}
+
+/**
+# Safety
+`text` must be NULL or a NUL terminated string.
+*/
+unsafe fn read_str<'a>(text: *const c_char, name: &str) -> Result<&'a str, Failure> {
+    if text.is_null() {
+        return Err((RubikStatus::NullArgument, format!("{} is NULL", name)));
+    }
+    CStr::from_ptr(text).to_str().map_err(|_| (RubikStatus::InvalidUtf8, format!("{} isn't UTF-8", name)))
+}
+
+/**
+# Safety
+`cube` must be NULL or a cube from this library.
+*/
+unsafe fn read_cube<'a>(cube: *const Cube) -> Result<&'a Cube, Failure> {
+    cube.as_ref().ok_or_else(|| (RubikStatus::NullArgument, "cube is NULL".to_string()))
+}
+
+// A solved cube, NULL only if the library fails
+#[no_mangle]
+pub extern "C" fn rubik_cube_new() -> *mut Cube {
+    panic::catch_unwind(|| Box::into_raw(Box::new(Cube::new(None).expect("Error initializing cube"))))
+        .unwrap_or(ptr::null_mut())
+}
+
+/**
+The cube with the 54 stickers of `facelets` (see `Cube::from_facelets`), in `*cube`.
+# Safety
+`facelets` must be a NUL terminated string, `cube` valid for writes and `error` NULL or valid
+for writes.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn rubik_cube_from_facelets(facelets: *const c_char, cube: *mut *mut Cube, error: *mut *mut c_char) -> RubikStatus {
+    guard(error, || {
+        let facelets = read_str(facelets, "facelets")?;
+        if cube.is_null() {
+            return Err((RubikStatus::NullArgument, "cube is NULL".to_string()));
+        }
+        let parsed = Cube::from_facelets(facelets).map_err(|e| (RubikStatus::InvalidFacelets, e))?;
+        *cube = Box::into_raw(Box::new(parsed));
+        Ok(())
+    })
+}
+
+/**
+# Safety
+`cube` must be NULL or a cube from this library, not used afterwards.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn rubik_cube_free(cube: *mut Cube) {
+    if !cube.is_null() {
+        drop(Box::from_raw(cube));
+    }
+}
+
+/**
+Turn the cube by `moves` ("R U R' U'"). The cube doesn't change when they can't be parsed.
+# Safety
+`cube` must be a cube from this library, `moves` a NUL terminated string and `error` NULL or
+valid for writes.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn rubik_cube_apply(cube: *mut Cube, moves: *const c_char, error: *mut *mut c_char) -> RubikStatus {
+    guard(error, || {
+        let moves = parse_moves(read_str(moves, "moves")?).map_err(|e| (RubikStatus::InvalidMoves, e))?;
+        let cube = cube.as_mut().ok_or_else(|| (RubikStatus::NullArgument, "cube is NULL".to_string()))?;
+        apply_moves(cube, &moves);
+        Ok(())
+    })
+}
+
+/**
+RUBIK_OK when moves can solve the cube, RUBIK_UNSOLVABLE otherwise (see `Cube::is_solvable`).
+# Safety
+`cube` must be a cube from this library and `error` NULL or valid for writes.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn rubik_cube_validate(cube: *const Cube, error: *mut *mut c_char) -> RubikStatus {
+    guard(error, || {
+        if read_cube(cube)?.is_solvable() {
+            Ok(())
+        } else {
+            Err((RubikStatus::Unsolvable, SolveError::Unsolvable.to_string()))
+        }
+    })
+}
+
+/**
+1 when the cube is solved, 0 when it isn't, -1 for a NULL cube.
+# Safety
+`cube` must be NULL or a cube from this library.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn rubik_cube_is_solved(cube: *const Cube) -> c_int {
+    match cube.as_ref() {
+        Some(cube) => panic::catch_unwind(|| cube.is_solved() as c_int).unwrap_or(-1),
+        None => -1,
+    }
+}
+
+/**
+The 54 stickers of the cube (see `Cube::facelets`), NULL for a NULL cube.
+# Safety
+`cube` must be NULL or a cube from this library.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn rubik_cube_facelets(cube: *const Cube) -> *mut c_char {
+    match cube.as_ref() {
+        Some(cube) => panic::catch_unwind(|| to_c_string(&cube.facelets())).unwrap_or(ptr::null_mut()),
+        None => ptr::null_mut(),
+    }
+}
+
+/**
+Solve the cube within `time_limit_ms` (see `solver`), the moves go to `*solution`.
+- `method` is "roux", "zz" or "optimal", roux when NULL.
+- `metric` is "htm", "qtm" or "stm" for the optimal search, htm when NULL.
+- A `time_limit_ms` of 0 always gives `RubikStatus::Timeout`, even for a solved cube.
+# Safety
+`cube` must be a cube from this library, `method` and `metric` NULL or NUL terminated strings,
+`solution` valid for writes and `error` NULL or valid for writes.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn rubik_solve(
+    cube: *const Cube,
+    method: *const c_char,
+    metric: *const c_char,
+    time_limit_ms: u32,
+    solution: *mut *mut c_char,
+    error: *mut *mut c_char,
+) -> RubikStatus {
+    guard(error, || {
+        let cube = read_cube(cube)?;
+        let method = if method.is_null() { "roux" } else { read_str(method, "method")? };
+        let metric = if metric.is_null() { "htm" } else { read_str(metric, "metric")? };
+        if solution.is_null() {
+            return Err((RubikStatus::NullArgument, "solution is NULL".to_string()));
+        }
+        let method = SolveMethod::parse(method, metric).map_err(|e| (RubikStatus::InvalidMethod, e))?;
+        match solve_timed(cube, method, Duration::from_millis(time_limit_ms as u64)) {
+            Ok(solved) => {
+                *solution = to_c_string(&format_moves(&solved.moves));
+                Ok(())
+            }
+            Err(e) => {
+                let status = match e {
+                    SolveError::Unsolvable => RubikStatus::Unsolvable,
+                    SolveError::NoSolution(_) => RubikStatus::NoSolution,
+                    SolveError::TimedOut { .. } => RubikStatus::Timeout,
+                    SolveError::Crashed => RubikStatus::Panic,
+                };
+                Err((status, e.to_string()))
+            }
+        }
+    })
+}
+
+/**
+# Safety
+`text` must be NULL or a string from this library, not used afterwards.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn rubik_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+// A static description of a status code, never to be freed
+#[no_mangle]
+pub extern "C" fn rubik_status_message(status: c_int) -> *const c_char {
+    let message: &'static CStr = match status {
+        0 => c"OK",
+        1 => c"NULL argument",
+        2 => c"String isn't UTF-8",
+        3 => c"Invalid facelets",
+        4 => c"Invalid moves",
+        5 => c"Unknown method or metric",
+        6 => c"The cube can't be solved",
+        7 => c"No solution found",
+        8 => c"Time limit reached",
+        9 => c"Internal error",
+        _ => c"Unknown status",
+    };
+    message.as_ptr()
+}
@@ -0,0 +1,307 @@
+/*!
+* Fewest moves (FMC) assistant
+* - EO: orient the edges relative to one axis, on the normal or the inverse scramble.
+* - DR (domino reduction): from EO on one axis, orient the corners and edges relative to another
+*   axis and put its middle slice edges in the middle slice, so the rest can be solved with
+*   quarter turns of that axis and half turns of the others.
+* - NISS: moves found on the inverse scramble are added to the end of the solution, inverted.
+* - Insertions: a skeleton that leaves a 3-cycle of pieces is finished by inserting a commutator
+*   where it cancels the most moves.
+*/
+
+use crate::commutator::{find_commutators_with, Commutator, FingerCost};
+use crate::cube::{Axis, Cube, Piece, RotationDirection};
+use crate::notation::{apply_moves, face_moves, format_moves, invert_moves, simplify_moves, Layer, Metric, Move};
+use crate::stage::{alg_perm, compose, identity, invert, solve_stage, CoordTable, Perm};
+use std::fmt;
+use std::sync::OnceLock;
+
+const EO_MAX_DEPTH: usize = 7;
+const DR_MAX_DEPTH: usize = 14;
+const INSERTION_CANDIDATES: usize = 50;
+
+pub const AXES: [Axis; 3] = [Axis::UD, Axis::RL, Axis::FB];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Normal,
+    Inverse,
+}
+
+/**
+A solution in progress with NISS.
+- `normal` moves are solved on the scramble, `inverse` moves on the inverse scramble.
+- The full solution is the normal moves followed by the inverse moves, inverted.
+*/
+#[derive(Clone, Debug)]
+pub struct Niss {
+    pub scramble: Vec<Move>,
+    pub normal: Vec<Move>,
+    pub inverse: Vec<Move>,
+}
+
+impl Niss {
+    pub fn new(scramble: &[Move]) -> Self {
+        Niss { scramble: scramble.to_vec(), normal: Vec::new(), inverse: Vec::new() }
+    }
+
+    pub fn push(&mut self, side: Side, moves: &[Move]) {
+        let sequence = match side {
+            Side::Normal => &mut self.normal,
+            Side::Inverse => &mut self.inverse,
+        };
+        *sequence = simplify_moves(&[sequence.as_slice(), moves].concat());
+    }
+
+    // Cube to keep solving on one side: the moves of the other side are applied as premoves
+    pub fn cube(&self, side: Side) -> Cube {
+        let mut cube = Cube::new(None).expect("Error initializing cube");
+        match side {
+            Side::Normal => {
+                apply_moves(&mut cube, &invert_moves(&self.inverse));
+                apply_moves(&mut cube, &self.scramble);
+                apply_moves(&mut cube, &self.normal);
+            }
+            Side::Inverse => {
+                apply_moves(&mut cube, &invert_moves(&self.normal));
+                apply_moves(&mut cube, &invert_moves(&self.scramble));
+                apply_moves(&mut cube, &self.inverse);
+            }
+        }
+        cube
+    }
+
+    // Normal moves then the inverse moves inverted, merged where they cancel
+    pub fn solution(&self) -> Vec<Move> {
+        simplify_moves(&[self.normal.clone(), invert_moves(&self.inverse)].concat())
+    }
+
+    // Move count (HTM) of the final solution, or an error when it doesn't solve the scramble
+    pub fn check(&self) -> Result<usize, String> {
+        let solution = self.solution();
+        if solves(&self.scramble, &solution) {
+            Ok(Metric::Htm.count(&solution))
+        } else {
+            Err("The solution doesn't solve the scramble".to_string())
+        }
+    }
+}
+
+impl fmt::Display for Niss {
+    // Inverse moves are written in parentheses: "R U F (D' L)"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format_moves(&self.normal))?;
+        if !self.inverse.is_empty() {
+            let space = if self.normal.is_empty() { "" } else { " " };
+            write!(f, "{}({})", space, format_moves(&self.inverse))?;
+        }
+        Ok(())
+    }
+}
+
+// True when the solution solves the scramble
+pub fn solves(scramble: &[Move], solution: &[Move]) -> bool {
+    let mut cube = Cube::new(None).expect("Error initializing cube");
+    apply_moves(&mut cube, scramble);
+    apply_moves(&mut cube, solution);
+    cube.is_solved()
+}
+
+// Face moves that keep the edge orientation of an axis: half turns only on the faces of that axis
+pub fn eo_preserving_moves(axis: Axis) -> Vec<Move> {
+    face_moves().into_iter()
+        .filter(|m| match m.layer {
+            Layer::Face(face) => {
+                Axis::of_face(face as usize / Cube::FACE_SIZE) != axis || m.direction == RotationDirection::DoubleTurn
+            }
+            Layer::Slice(_) => false,
+        })
+        .collect()
+}
+
+// Flipped edges relative to an axis as a 12 bit number
+fn eo_key(cube: &Cube, axis: Axis) -> u64 {
+    Cube::labelled_flipped_edges(cube, axis).iter()
+        .fold(0, |acc, &flipped| (acc << 1) | flipped as u64)
+}
+
+// Corner orientation of a labelled cube for an axis: 2 bits per corner slot, telling which of its
+// stickers holds a sticker of the axis faces
+fn co_key(cube: &Cube, axis: Axis) -> u64 {
+    let on_axis = |label: u8| Axis::of_face(label as usize / Cube::FACE_SIZE) == axis;
+    Cube::CORNER_FACELETS.iter().fold(0, |acc, slot| {
+        let twist = slot.iter().position(|&s| on_axis(cube.state[s])).unwrap_or(0);
+        (acc << 2) | twist as u64
+    })
+}
+
+// Edge slots that hold a middle slice edge of the axis (an edge with no sticker on the axis faces)
+fn slice_key(cube: &Cube, axis: Axis) -> u64 {
+    let on_axis = |label: u8| Axis::of_face(label as usize / Cube::FACE_SIZE) == axis;
+    Cube::EDGE_FACELETS.iter().fold(0, |acc, slot| {
+        let middle = slot.iter().all(|&s| !on_axis(cube.state[s]));
+        (acc << 1) | middle as u64
+    })
+}
+
+// Fn pointers for CoordTable, one per axis (the index into AXES)
+fn eo_key_of<const AXIS: usize>(cube: &Cube) -> u64 {
+    eo_key(cube, AXES[AXIS])
+}
+
+fn co_key_of<const AXIS: usize>(cube: &Cube) -> u64 {
+    co_key(cube, AXES[AXIS])
+}
+
+fn slice_key_of<const AXIS: usize>(cube: &Cube) -> u64 {
+    slice_key(cube, AXES[AXIS])
+}
+
+fn key_fn(axis: Axis, keys: [fn(&Cube) -> u64; 3]) -> fn(&Cube) -> u64 {
+    keys[AXES.iter().position(|&a| a == axis).unwrap()]
+}
+
+struct AxisTables {
+    eo: CoordTable,
+    co: CoordTable,
+    slice: CoordTable,
+}
+
+static TABLES: [OnceLock<AxisTables>; 3] = [OnceLock::new(), OnceLock::new(), OnceLock::new()];
+
+// Built with all face moves, so the distances are lower bounds for the restricted DR moves too
+fn tables(axis: Axis) -> &'static AxisTables {
+    let index = AXES.iter().position(|&a| a == axis).unwrap();
+    TABLES[index].get_or_init(|| {
+        let moves = face_moves();
+        AxisTables {
+            eo: CoordTable::new(key_fn(axis, [eo_key_of::<0>, eo_key_of::<1>, eo_key_of::<2>]), &moves, EO_MAX_DEPTH),
+            co: CoordTable::new(key_fn(axis, [co_key_of::<0>, co_key_of::<1>, co_key_of::<2>]), &moves, DR_MAX_DEPTH),
+            slice: CoordTable::new(key_fn(axis, [slice_key_of::<0>, slice_key_of::<1>, slice_key_of::<2>]), &moves, DR_MAX_DEPTH),
+        }
+    })
+}
+
+// True when the labelled cube is in domino reduction for the axis
+pub fn is_domino(labelled: &Cube, axis: Axis) -> bool {
+    let solved = identity();
+    co_key(labelled, axis) == co_key(&solved, axis)
+        && slice_key(labelled, axis) == slice_key(&solved, axis)
+        && AXES.iter().filter(|&&a| a != axis).all(|&a| eo_key(labelled, a) == 0)
+}
+
+/**
+Shortest EO for an axis.
+- Returns an error when the cube can't be labelled.
+*/
+pub fn find_eo(cube: &Cube, axis: Axis) -> Result<Vec<Move>, String> {
+    let labelled = cube.labelled().ok_or("Invalid cube: pieces can't be identified")?;
+    solve_stage(&labelled, &face_moves(), &|c| eo_key(c, axis) == 0, &[&tables(axis).eo], EO_MAX_DEPTH)
+        .ok_or_else(|| "EO not found".to_string())
+}
+
+// Shortest EO on every axis, on both sides of a NISS solution
+pub fn find_all_eo(niss: &Niss) -> Result<Vec<(Side, Axis, Vec<Move>)>, String> {
+    let mut found = Vec::new();
+    for side in [Side::Normal, Side::Inverse] {
+        let cube = niss.cube(side);
+        for axis in AXES {
+            found.push((side, axis, find_eo(&cube, axis)?));
+        }
+    }
+    found.sort_by_key(|(_, _, moves)| moves.len());
+    Ok(found)
+}
+
+/**
+Shortest DR on `dr_axis` for a cube with the edges oriented on `eo_axis`.
+- Only uses moves that keep the EO, so EO + DR can be read as one skeleton.
+- Returns an error when the axes are the same, the EO isn't solved or nothing is found.
+*/
+pub fn find_dr(cube: &Cube, eo_axis: Axis, dr_axis: Axis) -> Result<Vec<Move>, String> {
+    if eo_axis == dr_axis {
+        return Err("DR needs an axis different from the EO axis".to_string());
+    }
+    let labelled = cube.labelled().ok_or("Invalid cube: pieces can't be identified")?;
+    if eo_key(&labelled, eo_axis) != 0 {
+        return Err(format!("Edges are not oriented on {:?}", eo_axis));
+    }
+    let tables = tables(dr_axis);
+    solve_stage(
+        &labelled,
+        &eo_preserving_moves(eo_axis),
+        &|c| is_domino(c, dr_axis),
+        &[&tables.co, &tables.slice],
+        DR_MAX_DEPTH,
+    )
+        .ok_or_else(|| "DR not found".to_string())
+}
+
+#[derive(Clone, Debug)]
+pub struct Insertion {
+    pub position: usize, // Number of skeleton moves before the insertion
+    pub commutator: Commutator,
+    pub solution: Vec<Move>, // Skeleton with the commutator inserted, after cancellations
+}
+
+/**
+Finish a skeleton that leaves a 3-cycle of corners (or of edges) by inserting a commutator.
+- Every position of the skeleton is tried, with the shortest commutators for the 3-cycle there.
+  Commutators only use face moves, as FMC solutions are written and counted in HTM.
+- Returns the insertions whose final solution is shortest in HTM first; each one is checked to
+  solve the scramble.
+*/
+pub fn find_insertions(scramble: &[Move], skeleton: &[Move], max_results: usize) -> Result<Vec<Insertion>, String> {
+    let mut cube = Cube::new(None).expect("Error initializing cube");
+    apply_moves(&mut cube, scramble);
+    let start = cube.labelled().ok_or("Invalid cube: pieces can't be identified")?;
+    // Sticker permutation of the scramble, from its labelled cube
+    let scramble_perm = invert(&start.state);
+
+    let cost = FingerCost::default();
+    let moves = face_moves();
+    let mut found = Vec::new();
+    for position in 0..=skeleton.len() {
+        let (before, after) = skeleton.split_at(position);
+        // scramble + before + X + after must be solved
+        let reached = compose(&scramble_perm, &alg_perm(before));
+        let wanted: Perm = compose(&invert(&reached), &invert(&alg_perm(after)));
+        let (buffer, first, second) = three_cycle(&wanted).ok_or("The skeleton doesn't leave a 3-cycle")?;
+
+        for commutator in find_commutators_with(buffer, first, second, &moves, &cost, INSERTION_CANDIDATES)? {
+            let solution = simplify_moves(&[before.to_vec(), commutator.moves(), after.to_vec()].concat());
+            found.push(Insertion { position, commutator, solution });
+        }
+    }
+
+    found.retain(|insertion| solves(scramble, &insertion.solution));
+    found.sort_by_key(|insertion| Metric::Htm.count(&insertion.solution));
+    // Different insertions often cancel into the same solution, keep the first one
+    let mut seen = Vec::new();
+    found.retain(|insertion| {
+        let new = !seen.contains(&insertion.solution);
+        seen.push(insertion.solution.clone());
+        new
+    });
+    found.truncate(max_results);
+    Ok(found)
+}
+
+// Three stickers (a, b, c) of the pieces of a 3-cycle with a -> b -> c, or None for any other permutation
+fn three_cycle(perm: &Perm) -> Option<(usize, usize, usize)> {
+    let moved: Vec<usize> = (0..Cube::CELL_COUNT).filter(|&p| perm[p] as usize != p).collect();
+    let first = *moved.first()?;
+    let pieces: Vec<Piece> = moved.iter().map(|&s| Cube::piece_of(s)).collect();
+    let corners = pieces.iter().all(|p| matches!(p, Piece::Corner(_)));
+    let edges = pieces.iter().all(|p| matches!(p, Piece::Edge(_)));
+    let size = if corners { 9 } else if edges { 6 } else { return None };
+    if moved.len() != size {
+        return None;
+    }
+    let second = perm[first] as usize;
+    let third = perm[second] as usize;
+    if perm[third] as usize != first {
+        return None;
+    }
+    Some((first, second, third))
+}
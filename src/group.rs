@@ -0,0 +1,121 @@
+/*!
+* Permutation group generated by a set of moves (Schreier-Sims algorithm)
+* - The group is stored as a stabilizer chain: level k holds the permutations that fix the base
+*   points of the levels before it, with one permutation for every place the base point of level k
+*   can be sent to (the transversal).
+* - A permutation belongs to the group when it can be sifted down the chain to the identity.
+* - This tells exactly which states can be reached with a move set, e.g. only 1/6 of the corner
+*   permutations of the R and U layers are reachable with <R, U>.
+*/
+
+use crate::notation::Move;
+use crate::stage::{compose, identity_perm, invert, move_perm, Perm};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+struct Level {
+    base: usize,
+    generators: Vec<Perm>,             // Generators added at this level, they fix the base points before it
+    transversal: HashMap<usize, Perm>, // Orbit point -> permutation sending the base point there
+}
+
+pub struct PermGroup {
+    levels: Vec<Level>,
+}
+
+impl PermGroup {
+    pub fn generated_by(moves: &[Move]) -> Self {
+        let mut group = PermGroup { levels: Vec::new() };
+        let mut verified: Vec<bool> = Vec::new();
+        for perm in moves.iter().map(move_perm) {
+            if group.sift(&perm, 0).0 != identity_perm() {
+                group.add_generator(perm, &mut verified);
+            }
+        }
+
+        // Every Schreier generator of every level must sift to the identity, deepest levels first
+        while let Some(k) = verified.iter().rposition(|&done| !done) {
+            match group.failing_schreier_generator(k) {
+                Some(residue) => group.add_generator(residue, &mut verified),
+                None => verified[k] = true,
+            }
+        }
+        group
+    }
+
+    // Number of permutations in the group
+    pub fn order(&self) -> u128 {
+        self.levels.iter().map(|level| level.transversal.len() as u128).product()
+    }
+
+    pub fn contains(&self, perm: &Perm) -> bool {
+        self.sift(perm, 0).0 == identity_perm()
+    }
+
+    // Strip `perm` level by level from `start`, returns what is left and the level where it stopped
+    fn sift(&self, perm: &Perm, start: usize) -> (Perm, usize) {
+        let mut perm = *perm;
+        for (k, level) in self.levels.iter().enumerate().skip(start) {
+            match level.transversal.get(&(perm[level.base] as usize)) {
+                Some(u) => perm = compose(&perm, &invert(u)),
+                None => return (perm, k),
+            }
+        }
+        (perm, self.levels.len())
+    }
+
+    // Add a permutation that doesn't sift through, at the level where it stopped
+    fn add_generator(&mut self, perm: Perm, verified: &mut Vec<bool>) {
+        let (residue, k) = self.sift(&perm, 0);
+        if k == self.levels.len() {
+            let base = (0..residue.len()).find(|&p| residue[p] as usize != p).expect("Residue must move a point");
+            let mut transversal = HashMap::new();
+            transversal.insert(base, identity_perm());
+            self.levels.push(Level { base, generators: Vec::new(), transversal });
+            verified.push(false);
+        }
+        self.levels[k].generators.push(residue);
+        // The groups of the levels up to k got bigger
+        for (level, done) in verified.iter_mut().enumerate().take(k + 1) {
+            self.update_orbit(level);
+            *done = false;
+        }
+    }
+
+    // Generators of level k: its own and the ones of the deeper levels
+    fn level_generators(&self, k: usize) -> Vec<Perm> {
+        self.levels[k..].iter().flat_map(|level| level.generators.iter().copied()).collect()
+    }
+
+    fn update_orbit(&mut self, k: usize) {
+        let generators = self.level_generators(k);
+        let level = &mut self.levels[k];
+        let mut queue: Vec<usize> = level.transversal.keys().copied().collect();
+        while let Some(p) = queue.pop() {
+            let u = level.transversal[&p];
+            for g in &generators {
+                let q = g[p] as usize;
+                if let Entry::Vacant(entry) = level.transversal.entry(q) {
+                    entry.insert(compose(&u, g));
+                    queue.push(q);
+                }
+            }
+        }
+    }
+
+    // A Schreier generator of level k that doesn't sift through the deeper levels
+    fn failing_schreier_generator(&self, k: usize) -> Option<Perm> {
+        let level = &self.levels[k];
+        for (&p, u) in level.transversal.iter() {
+            for g in self.level_generators(k) {
+                let image = g[p] as usize;
+                let schreier = compose(&compose(u, &g), &invert(&level.transversal[&image]));
+                let (residue, _) = self.sift(&schreier, k + 1);
+                if residue != identity_perm() {
+                    return Some(residue);
+                }
+            }
+        }
+        None
+    }
+}
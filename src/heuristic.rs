@@ -33,10 +33,10 @@ impl PatternDB {
                 let new_cube = cube; // Avoid clone
                 new_cube.apply_move(face, direction);
 
-                if !pdb.contains_key(&new_cube) {
-                    pdb.insert(new_cube, depth + 1);
+                pdb.entry(new_cube).or_insert_with(|| {
                     queue.push_back((new_cube, depth + 1));
-                }
+                    depth + 1
+                });
             }
         }
 
@@ -61,7 +61,7 @@ fn misplaced_stickers(cube: &Cube) -> usize {
 The function caculates the Manhattan distance between the current position of each sticker and its solved position.
 Idea:
 - In 2D space, the Manhattan distance between two points is the sum of the absolute differences of their coordinates:
-    d(p, q) = |p.x - q.x| + |p.y - q.y|
+  d(p, q) = |p.x - q.x| + |p.y - q.y|
 - With the Rubik's Cube, we caculate sum of all Manhattan distances between the current position of each sticker and its solved position.
 - Because rotating a face of the cube can change some of the stickers' positions, the Manhattan distance doesn't heuristic correctly.
 - But heuristic use for estimate the number of moves to solve the cube.
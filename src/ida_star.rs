@@ -1,4 +1,4 @@
-/**
+/*!
 * Diameter of the Rubik's Cube Group is 20
 * The maximum number of moves to solve the Rubik's Cube is 20 if the move be chosen is the best move
 * In rubik space have more 43 trillion states
@@ -6,16 +6,183 @@
 
 use crate::cube::{Cube, Face, RotationDirection};
 use crate::heuristic::*;
-use std::collections::HashSet;
+use crate::notation::{Layer, Metric, Move};
+use crate::stage::{identity, reference_stickers, rotations, LowerBound, PieceTable, Solutions};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+const DEPTH_LIMIT: usize = 5;
+
+// Largest cost stored in the pattern databases of `ida_star_metric`
+const TABLE_MAX_COST: usize = 14;
+
+// Stickers changed by one face turn: 8 on the face and 3 on each of the 4 sides
+const STICKERS_PER_MOVE: usize = 20;
+
+/**
+What the search has to reach.
+- `Solved`: the solved cube, with the full heuristic.
+- `Target`: one exact cube, e.g. a checkerboard or the superflip ("from A to B").
+- `Pattern`: some stickers must have a given color, `None` stickers match anything
+  (only the cross, only the first two layers, ...).
+- `Predicate`: any test on the cube, the search then has no heuristic.
+*/
+pub enum Goal {
+    Solved,
+    Target(Cube),
+    Pattern([Option<u8>; Cube::CELL_COUNT]),
+    Predicate(Box<dyn Fn(&Cube) -> bool>),
+}
+
+impl Goal {
+    // Pattern of the solved cube that only keeps the given stickers
+    pub fn solved_stickers(stickers: &[usize]) -> Goal {
+        let solved = Cube::new(None).expect("Error initializing cube");
+        let mut pattern = [None; Cube::CELL_COUNT];
+        for &i in stickers {
+            pattern[i] = Some(solved.state[i]);
+        }
+        Goal::Pattern(pattern)
+    }
+
+    // Pattern of a masked cube: its unknown stickers match anything
+    pub fn masked(target: &Cube) -> Goal {
+        Goal::Pattern(known_stickers(target))
+    }
+
+    // Solved pattern for the colors of the cube's centers, every sticker of an unknown center matches anything
+    pub fn solved_like(cube: &Cube) -> Goal {
+        let mut pattern = [None; Cube::CELL_COUNT];
+        for (i, p) in pattern.iter_mut().enumerate() {
+            let center = cube.state[Cube::CENTERS[i / Cube::FACE_SIZE]];
+            *p = (center != Cube::UNKNOWN).then_some(center);
+        }
+        Goal::Pattern(pattern)
+    }
+
+    // D cross: the 4 D edges and the centers
+    pub fn cross() -> Goal {
+        let mut stickers = vec![10, 12, 14, 16, 43, 25, 34, 52];
+        stickers.extend(Cube::CENTERS);
+        Goal::solved_stickers(&stickers)
+    }
+
+    // First two layers: the D face and the two lower rows of the side faces
+    pub fn first_two_layers() -> Goal {
+        let mut stickers: Vec<usize> = (9..18).collect();
+        for face in [Face::R, Face::L, Face::F, Face::B] {
+            stickers.extend(face as usize + Cube::EDGE_SIZE..face as usize + Cube::FACE_SIZE);
+        }
+        stickers.push(Cube::CENTERS[0]);
+        Goal::solved_stickers(&stickers)
+    }
+
+    // Solved cube after the given moves
+    pub fn after_moves(moves: &[(Face, RotationDirection)]) -> Goal {
+        let mut cube = Cube::new(None).expect("Error initializing cube");
+        for &(face, direction) in moves {
+            cube.rotate(face, direction);
+        }
+        Goal::Target(cube)
+    }
+
+    // Every face shows a checkerboard
+    pub fn checkerboard() -> Goal {
+        use Face::*;
+        let half = RotationDirection::DoubleTurn;
+        Goal::after_moves(&[(R, half), (L, half), (U, half), (D, half), (F, half), (B, half)])
+    }
+
+    // Every edge flipped in place
+    pub fn superflip() -> Goal {
+        use Face::*;
+        use RotationDirection::*;
+        Goal::after_moves(&[
+            (U, Clockwise), (R, DoubleTurn), (F, Clockwise), (B, Clockwise), (R, Clockwise),
+            (B, DoubleTurn), (R, Clockwise), (U, DoubleTurn), (L, Clockwise), (B, DoubleTurn),
+            (R, Clockwise), (U, CounterClockwise), (D, CounterClockwise), (R, DoubleTurn), (F, Clockwise),
+            (R, CounterClockwise), (L, Clockwise), (B, DoubleTurn), (U, DoubleTurn), (F, DoubleTurn),
+        ])
+    }
+
+    pub fn is_reached(&self, cube: &Cube) -> bool {
+        match self {
+            Goal::Solved => cube.is_solved(),
+            Goal::Target(target) => cube.matches(target),
+            Goal::Pattern(pattern) => mismatched_stickers(cube, pattern) == 0,
+            Goal::Predicate(reached) => reached(cube),
+        }
+    }
+
+    // The heuristic only knows the solved cube, the other goals get a relaxed estimate
+    fn estimate(&self, cube: &Cube) -> usize {
+        match self {
+            Goal::Solved => combined_heuristic(cube),
+            Goal::Target(target) => mismatched_stickers(cube, &known_stickers(target)).div_ceil(STICKERS_PER_MOVE),
+            Goal::Pattern(pattern) => mismatched_stickers(cube, pattern).div_ceil(STICKERS_PER_MOVE),
+            Goal::Predicate(_) => 0,
+        }
+    }
+}
+
+// Stickers of a cube as a pattern, None for the unknown ones
+fn known_stickers(cube: &Cube) -> [Option<u8>; Cube::CELL_COUNT] {
+    cube.state.map(|c| (c != Cube::UNKNOWN).then_some(c))
+}
+
+// Known stickers of the cube that don't match the pattern
+fn mismatched_stickers(cube: &Cube, pattern: &[Option<u8>; Cube::CELL_COUNT]) -> usize {
+    pattern.iter().zip(cube.state.iter())
+        .filter(|(p, &c)| c != Cube::UNKNOWN && p.is_some_and(|p| p != c))
+        .count()
+}
+
+/**
+Going from `cube` to `target` is the same as solving `cube` after renaming every sticker by the
+sticker that sits at its position in `target`. Returns that renamed cube with the standard colors,
+so the full heuristic can be used, or None when a cube can't be labelled.
+*/
+fn relative_to(cube: &Cube, target: &Cube) -> Option<Cube> {
+    let cube = cube.labelled()?;
+    let target = target.labelled()?;
+    let mut position = [0; Cube::CELL_COUNT];
+    for (i, &label) in target.state.iter().enumerate() {
+        position[label as usize] = i;
+    }
+    let mut state = [0; Cube::CELL_COUNT];
+    for (i, &label) in cube.state.iter().enumerate() {
+        state[i] = (position[label as usize] / Cube::FACE_SIZE) as u8;
+    }
+    Some(Cube { state })
+}
 
 // Using IDA* algorithms to find answer
 pub fn ida_star(cube: &Cube) -> Option<Vec<(Face, RotationDirection)>> {
-    let mut bound = combined_heuristic(cube);
+    ida_star_goal(cube, &Goal::Solved, DEPTH_LIMIT)
+}
+
+/**
+IDA* towards any goal, with at most `depth_limit` moves.
+- A target cube that can be labelled is solved as a renamed cube, with the full heuristic.
+- Unknown stickers (Cube::UNKNOWN) of the cube or of the target match anything.
+*/
+pub fn ida_star_goal(cube: &Cube, goal: &Goal, depth_limit: usize) -> Option<Vec<(Face, RotationDirection)>> {
+    // The heuristic doesn't know unknown stickers: a masked cube is solved as a pattern
+    if cube.is_masked() && matches!(goal, Goal::Solved) {
+        return ida_star_goal(cube, &Goal::solved_like(cube), depth_limit);
+    }
+    if let Goal::Target(target) = goal {
+        if let Some(renamed) = relative_to(cube, target) {
+            return ida_star_goal(&renamed, &Goal::Solved, depth_limit);
+        }
+    }
+
+    let mut bound = goal.estimate(cube);
     let mut path = Vec::new();
     let mut best_state = *cube; // Lưu trạng thái tốt nhất tìm thấy
 
     loop {
-        let result = search(cube, 0, bound, &mut path, &mut HashSet::new(), 5, &mut best_state);
+        let result = search(cube, goal, 0, bound, &mut path, &mut HashMap::new(), depth_limit, &mut best_state);
 
         if result == 0 {
             return Some(path); // Found solution
@@ -29,12 +196,14 @@ pub fn ida_star(cube: &Cube) -> Option<Vec<(Face, RotationDirection)>> {
 }
 
 /// Find the solution using IDA* algorithms
+#[allow(clippy::too_many_arguments)]
 fn search(
     cube: &Cube,
+    goal: &Goal,
     g: usize,
     bound: usize,
     path: &mut Vec<(Face, RotationDirection)>,
-    visited: &mut HashSet<Cube>,
+    visited: &mut HashMap<Cube, usize>, // State -> fewest moves it was reached with
     depth_limit: usize,
     best_state: &mut Cube,
 ) -> usize {
@@ -42,13 +211,13 @@ fn search(
         return usize::MAX;
     }
 
-    let f = g + combined_heuristic(cube);
+    let f = g + goal.estimate(cube);
     if f > bound {
         // When value exceeds the bound, return the value
         // This not solution
         return f;
     }
-    if cube.is_solved() {
+    if goal.is_reached(cube) {
         // This is solution
         return 0;
     }
@@ -60,7 +229,7 @@ fn search(
         .map(|&(face, dir)| {
             let mut new_cube = *cube;
             new_cube.rotate(face, dir);
-            let new_h = combined_heuristic(&new_cube);
+            let new_h = goal.estimate(&new_cube);
             ((face, dir), new_cube, new_h)
         })
         .collect();
@@ -75,7 +244,10 @@ fn search(
         }
 
         // Avoid repeating states
-        if visited.contains(&new_cube) {
+        // With the relaxed estimate of the other goals, a state first reached by a long path must be
+        // tried again when a shorter path reaches it, or short solutions are missed
+        let revisit = !matches!(goal, Goal::Solved);
+        if visited.get(&new_cube).is_some_and(|&moves| !revisit || moves <= g + 1) {
             continue;
         }
 
@@ -83,9 +255,9 @@ fn search(
         // If the new state is visited successfully or not successfully
         // Both are added to the visited set because if is successful, it will be the solution
         // If not successful, it will be the state that has been visited and don't need to visit again
-        visited.insert(new_cube);
+        visited.insert(new_cube, g + 1);
 
-        let t = search(&new_cube, g + 1, bound, path, visited, depth_limit, best_state);
+        let t = search(&new_cube, goal, g + 1, bound, path, visited, depth_limit, best_state);
 
         if t < min_cost {
             min_cost = t;
@@ -102,3 +274,143 @@ fn search(
 
     min_cost
 }
+
+/**
+Size of the pattern databases of `ida_star_metric`: how many pieces each table tracks (corners
+and edges have their own tables) and the largest cost it stores.
+- The default, 4 pieces up to a cost of 14, makes 5 tables of 136080 to 190080 entries. With 2
+  or 3 pieces the tables take a small part of the memory and time to build, and the search
+  visits more nodes.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TableSize {
+    pub pieces: usize,
+    pub max_cost: usize,
+}
+
+impl Default for TableSize {
+    fn default() -> Self {
+        TableSize { pieces: 4, max_cost: TABLE_MAX_COST }
+    }
+}
+
+impl TableSize {
+    // Corners and edges of each table, in order
+    fn groups(&self) -> Vec<(Vec<usize>, Vec<usize>)> {
+        let pieces = self.pieces.max(1);
+        let corners = (0..8).collect::<Vec<_>>().chunks(pieces).map(|c| (c.to_vec(), Vec::new())).collect::<Vec<_>>();
+        let edges = (0..12).collect::<Vec<_>>().chunks(pieces).map(|e| (Vec::new(), e.to_vec())).collect::<Vec<_>>();
+        corners.into_iter().chain(edges).collect()
+    }
+}
+
+static HTM_TABLES: OnceLock<Arc<Vec<PieceTable>>> = OnceLock::new();
+static QTM_TABLES: OnceLock<Arc<Vec<PieceTable>>> = OnceLock::new();
+static STM_TABLES: OnceLock<Arc<Vec<PieceTable>>> = OnceLock::new();
+
+// Pattern databases where every distance is counted in the metric
+pub fn metric_tables(metric: Metric, size: TableSize) -> Vec<PieceTable> {
+    let moves = metric.moves();
+    size.groups().iter()
+        .map(|(corners, edges)| PieceTable::with_metric(&reference_stickers(corners, edges), &moves, metric, size.max_cost))
+        .collect()
+}
+
+// Tables of the default size for the metric, those of the named metrics are built once
+fn default_tables(metric: Metric) -> Arc<Vec<PieceTable>> {
+    let build = || Arc::new(metric_tables(metric, TableSize::default()));
+    match metric {
+        Metric::Htm => Arc::clone(HTM_TABLES.get_or_init(build)),
+        Metric::Qtm => Arc::clone(QTM_TABLES.get_or_init(build)),
+        Metric::Stm => Arc::clone(STM_TABLES.get_or_init(build)),
+        Metric::Custom(_) => build(),
+    }
+}
+
+// Build the tables of a named metric now rather than in the first search
+pub fn preload_tables(metric: Metric) {
+    default_tables(metric);
+}
+
+/**
+Optimal solution in a move metric: HTM (half turns count 1), QTM (half turns count 2) or STM
+(slice moves count 1), with a cost of at most `max_cost`.
+- The pattern databases are built for the same metric, so the solution is optimal in it.
+- Tables of the named metrics are built once, the ones of a custom metric on every call.
+- Returns None when the cube can't be labelled or no solution costs `max_cost` or less.
+*/
+pub fn ida_star_metric(cube: &Cube, metric: Metric, max_cost: usize) -> Option<Vec<Move>> {
+    solutions(cube, metric, max_cost)?.next()
+}
+
+/**
+Every solution costing at most `max_cost` in the metric, cheapest first.
+- Take the first k with `.take(k)`, the search goes on lazily after each solution.
+- Solutions that only differ by the order of commuting moves (R L / L R) are given once.
+- Returns None when the cube can't be labelled.
+*/
+pub fn solutions(cube: &Cube, metric: Metric, max_cost: usize) -> Option<Solutions<'static>> {
+    solutions_with_tables(cube, metric, default_tables(metric), max_cost)
+}
+
+/**
+Same as `solutions` with tables of another size, from `metric_tables` in the same metric.
+- With slice moves the centers move, and a cube solved in any orientation is solved: `M` is
+  solved by `M'` rather than `R' L`. The lower bound is then the smallest over the 24 orientations,
+  for a metric where a whole-cube rotation keeps the cost of every move (like STM).
+
+```
+use rubik::cube::Cube;
+use rubik::ida_star::{metric_tables, solutions_with_tables, TableSize};
+use rubik::notation::{apply_moves, parse_moves, Metric};
+use std::sync::Arc;
+
+let tables = Arc::new(metric_tables(Metric::Stm, TableSize { pieces: 2, max_cost: 4 }));
+for slice in ["M", "E", "S"] {
+    let mut cube = Cube::new(None).unwrap();
+    apply_moves(&mut cube, &parse_moves(slice).unwrap());
+    let mut search = solutions_with_tables(&cube, Metric::Stm, Arc::clone(&tables), 20).unwrap();
+    assert_eq!(search.next().map(|solution| solution.len()), Some(1));
+}
+```
+*/
+pub fn solutions_with_tables(cube: &Cube, metric: Metric, tables: Arc<Vec<PieceTable>>, max_cost: usize) -> Option<Solutions<'static>> {
+    let labelled = cube.labelled()?;
+    let moves = metric.moves();
+    let goals: Vec<Cube> = match moves.iter().any(|m| matches!(m.layer, Layer::Slice(_))) {
+        true => rotations().to_vec(),
+        false => vec![identity()],
+    };
+    // Turning a cube back from the orientation of a goal makes its distance the one in the tables
+    let turn_back: Vec<[u8; Cube::CELL_COUNT]> = goals.iter()
+        .map(|goal| {
+            let mut back = [0; Cube::CELL_COUNT];
+            for (to, &from) in goal.state.iter().enumerate() {
+                back[from as usize] = to as u8;
+            }
+            back
+        })
+        .collect();
+    // Lower bound of the cost in the metric, from the largest estimate
+    let estimate = move |c: &Cube| {
+        turn_back.iter()
+            .map(|back| {
+                let mut turned = *c;
+                turned.gather(back);
+                tables.iter().map(|t| t.estimate(&turned)).max().unwrap_or(0)
+            })
+            .min()
+            .unwrap_or(0)
+    };
+    Some(Solutions::new(&labelled, &moves, metric, Box::new(move |c| goals.contains(c)), Box::new(estimate), max_cost))
+}
+
+// All the optimal solutions in the metric, None when there is none within `max_cost`
+pub fn optimal_solutions(cube: &Cube, metric: Metric, max_cost: usize) -> Option<Vec<Vec<Move>>> {
+    let mut solutions = solutions(cube, metric, max_cost)?;
+    let first = solutions.next()?;
+    let cost = metric.count(&first);
+    let mut optimal = vec![first];
+    optimal.extend(solutions.take_while(|s| metric.count(s) == cost));
+    Some(optimal)
+}
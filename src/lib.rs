@@ -0,0 +1,36 @@
+pub mod cube;
+pub mod cubie;
+pub mod movetable;
+pub mod ida_star;
+pub mod heuristic;
+pub mod notation;
+pub mod stage;
+pub mod roux;
+pub mod zz;
+pub mod bld;
+pub mod commutator;
+pub mod fmc;
+pub mod group;
+pub mod restricted;
+pub mod llgen;
+pub mod cases;
+pub mod train;
+pub mod json;
+#[cfg(feature = "native")]
+pub mod session;
+pub mod recon;
+pub mod render;
+pub mod svg;
+#[cfg(feature = "native")]
+pub mod playback;
+#[cfg(feature = "native")]
+pub mod repl;
+#[cfg(feature = "native")]
+pub mod solver;
+#[cfg(feature = "native")]
+pub mod server;
+#[cfg(feature = "native")]
+pub mod ffi;
+#[cfg(feature = "native")]
+pub mod batch;
+pub mod wasm;
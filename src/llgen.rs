@@ -0,0 +1,189 @@
+/*!
+* Algorithm generator for last layer cases (OLL, PLL, ZBLL, ...)
+* - A case is a cube where only the U layer is unsolved, given as a cube or by its case ID.
+* - Algorithms are searched with restricted move sets (<R, U>, <R, U, F>, ...), every AUF before
+*   and after the algorithm is tried, and algorithms that only differ by their AUFs are merged.
+* - Each algorithm gets an ergonomic score: lower is better.
+*/
+
+use crate::cases::{apply_auf, aufs, case_by_id};
+use crate::commutator::FingerCost;
+use crate::cube::{Cube, Face, RotationDirection};
+use crate::group::PermGroup;
+use crate::notation::{apply_moves, format_moves, invert_moves, layer_moves, parse_moves, Layer, Metric, Move};
+use crate::stage::{compose, identity, invert, is_home, piece_stickers, positions, reference_stickers, PieceTable, Solutions};
+use std::fmt;
+
+// Pieces that must stay solved (indices into Cube::CORNER_FACELETS / Cube::EDGE_FACELETS)
+const F2L_CORNERS: [usize; 4] = [4, 5, 6, 7];
+const F2L_EDGES: [usize; 8] = [4, 5, 6, 7, 8, 9, 10, 11];
+const LL_PIECES: [usize; 4] = [0, 1, 2, 3]; // U corners or U edges
+
+const TABLE_MAX_DEPTH: usize = 10;
+
+/**
+Ergonomic model of an algorithm, the score is a weighted sum:
+- `fingers`: finger trick cost of each move (R, U and M are the cheapest by default).
+- `move_weight` for each move.
+- `regrip` for each regrip: the right wrist ends a move turned half a turn away from its home
+  position, or an F, B, D or L move comes while it is turned.
+- `non_ru` for each move that is not R or U.
+*/
+#[derive(Clone, Debug)]
+pub struct Ergonomics {
+    pub fingers: FingerCost,
+    pub move_weight: u32,
+    pub regrip: u32,
+    pub non_ru: u32,
+}
+
+impl Default for Ergonomics {
+    fn default() -> Self {
+        Ergonomics { fingers: FingerCost::default(), move_weight: 2, regrip: 4, non_ru: 1 }
+    }
+}
+
+impl Ergonomics {
+    pub fn score(&self, moves: &[Move]) -> u32 {
+        let non_ru = moves.iter().filter(|m| !is_ru(m)).count() as u32;
+        self.fingers.cost(moves) + self.move_weight * moves.len() as u32 + self.regrip * regrips(moves) + self.non_ru * non_ru
+    }
+}
+
+fn is_ru(m: &Move) -> bool {
+    matches!(m.layer, Layer::Face(Face::R) | Layer::Face(Face::U))
+}
+
+// Regrips of the right hand, following the wrist in quarter turns away from home
+pub fn regrips(moves: &[Move]) -> u32 {
+    let mut wrist: i32 = 0;
+    let mut count = 0;
+    for m in moves {
+        match m.layer {
+            Layer::Face(Face::R) => {
+                wrist += match m.direction {
+                    RotationDirection::Clockwise => 1,
+                    RotationDirection::CounterClockwise => -1,
+                    RotationDirection::DoubleTurn => 2,
+                };
+                if wrist.abs() >= 2 {
+                    count += 1;
+                    wrist = 0;
+                }
+            }
+            Layer::Face(Face::U) | Layer::Slice(_) => {}
+            Layer::Face(_) => {
+                if wrist != 0 {
+                    count += 1;
+                    wrist = 0;
+                }
+            }
+        }
+    }
+    count
+}
+
+#[derive(Clone, Debug)]
+pub struct LlAlgorithm {
+    pub pre_auf: Option<Move>, // U turn before the algorithm
+    pub moves: Vec<Move>,
+    pub post_auf: Option<Move>, // U turn after it
+    pub score: u32,
+}
+
+impl fmt::Display for LlAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let auf = |m: Option<Move>| m.map(|m| format!("({}) ", m)).unwrap_or_default();
+        write!(f, "{}{}", auf(self.pre_auf), format_moves(&self.moves))?;
+        if let Some(m) = self.post_auf {
+            write!(f, " ({})", m)?;
+        }
+        write!(f, " [{} moves, score {}]", self.moves.len(), self.score)
+    }
+}
+
+// Case given by its ID in the case database ("OLL 27", "PLL Ua", "Ua"), or by an algorithm that solves it
+pub fn case_cube(case: &str) -> Result<Cube, String> {
+    if let Some(case) = case_by_id(case) {
+        return Ok(case.cube());
+    }
+    let mut cube = Cube::new(None).expect("Error initializing cube");
+    apply_moves(&mut cube, &invert_moves(&parse_moves(case)?));
+    Ok(cube)
+}
+
+// The move sets tried by default: <R, U>, <R, U, F>, <R, U, D>, <R, U, L>
+pub fn default_move_sets() -> Vec<Vec<Move>> {
+    let face = Layer::Face;
+    [Face::F, Face::D, Face::L].iter()
+        .map(|&extra| layer_moves(&[face(Face::R), face(Face::U), face(extra)]))
+        .chain([layer_moves(&[face(Face::R), face(Face::U)])])
+        .collect()
+}
+
+/**
+Find algorithms for a last layer case, with at most `max_length` moves.
+- Each move set and each AUF before the algorithm is searched separately, keeping up to
+  `per_search` algorithms of each search, shortest first.
+- Algorithms that start with a U turn are left out: they are an AUF of another one.
+- Returns the algorithms from the best score to the worst, or an error when the cube can't be
+  labelled or more than the U layer is unsolved.
+*/
+pub fn generate_algorithms(
+    case: &Cube,
+    move_sets: &[Vec<Move>],
+    max_length: usize,
+    ergonomics: &Ergonomics,
+    per_search: usize,
+) -> Result<Vec<LlAlgorithm>, String> {
+    let labelled = case.labelled().ok_or("Invalid cube: pieces can't be identified")?;
+    if !is_home(&labelled, &piece_stickers(&F2L_CORNERS, &F2L_EDGES)) || !is_home(&labelled, &Cube::CENTERS) {
+        return Err("Not a last layer case: only the U layer may be unsolved".to_string());
+    }
+
+    let solved: Vec<Cube> = aufs().iter().map(|&auf| apply_auf(&identity(), auf)).collect();
+    let mut found: Vec<LlAlgorithm> = Vec::new();
+    for moves in move_sets {
+        let group = PermGroup::generated_by(moves);
+        let tables = [
+            PieceTable::new(&reference_stickers(&F2L_CORNERS, &[]), moves, TABLE_MAX_DEPTH),
+            PieceTable::new(&reference_stickers(&[], &F2L_EDGES[..4]), moves, TABLE_MAX_DEPTH),
+            PieceTable::new(&reference_stickers(&[], &F2L_EDGES[4..]), moves, TABLE_MAX_DEPTH),
+            PieceTable::with_goals(&reference_stickers(&LL_PIECES, &[]), &solved, moves, TABLE_MAX_DEPTH),
+            PieceTable::with_goals(&reference_stickers(&[], &LL_PIECES), &solved, moves, TABLE_MAX_DEPTH),
+        ];
+
+        for pre_auf in aufs() {
+            let start = apply_auf(&labelled, pre_auf);
+            // The cube after the algorithm is solved up to a U turn
+            let reachable = solved.iter().any(|target| {
+                let remaining = compose(&start.state, &invert(&target.state));
+                group.contains(&remaining)
+            });
+            if !reachable {
+                continue;
+            }
+
+            let goal = |c: &Cube| solved.contains(c);
+            let estimate = |c: &Cube| {
+                let position = positions(c);
+                tables.iter().map(|t| t.estimate_at(&position)).max().unwrap_or(0)
+            };
+            let algorithms = Solutions::new(&start, moves, Metric::Stm, Box::new(goal), Box::new(estimate), max_length)
+                .filter(|alg| alg.first().is_some_and(|m| m.layer != Layer::Face(Face::U)))
+                .take(per_search);
+            for alg in algorithms {
+                if found.iter().any(|other| other.moves == alg) {
+                    continue;
+                }
+                let mut end = start;
+                apply_moves(&mut end, &alg);
+                let post_auf = aufs().into_iter().find(|&auf| apply_auf(&end, auf) == identity()).flatten();
+                found.push(LlAlgorithm { pre_auf, score: ergonomics.score(&alg), moves: alg, post_auf });
+            }
+        }
+    }
+
+    found.sort_by_key(|alg| (alg.score, alg.moves.len()));
+    Ok(found)
+}
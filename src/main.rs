@@ -1,11 +1,507 @@
-pub mod cube;
-pub mod ida_star;
-pub mod heuristic;
-
-use crate::cube::*;
-use crate::ida_star::*;
+use rubik::batch::{run_batch, BatchConfig, BatchFormat};
+use rubik::bld::{solve_bld, Buffers, Execution};
+use rubik::cases::{case_by_id, recognize, CaseSet};
+use rubik::session::{load_sessions, parse_duration, save_sessions, Penalty, Session, Solve};
+use rubik::train::{is_right_answer, new_drill, Rng, Stats};
+use rubik::cube::*;
+use rubik::fmc::{find_all_eo, find_dr, Niss, AXES};
+use rubik::ida_star::*;
+use rubik::llgen::{case_cube, default_move_sets, generate_algorithms, Ergonomics};
+use rubik::notation::{apply_moves, format_moves, parse_moves, Metric, Move};
+use rubik::recon::{parse_turns, reconstruct};
+use rubik::render::{ColorMode, ColorScheme, Renderer};
+use rubik::svg::{isometric_svg, last_layer_svg, net_svg};
+use rubik::playback::{stdin_lines, Playback};
+use rubik::repl::Repl;
+use rubik::server::{serve, ServerConfig};
+use rubik::solver::SolveMethod;
+use rubik::restricted::{format_move_set, parse_move_set, solve_with_moves};
+use rubik::roux::solve_roux;
+use rubik::stage::MethodSolution;
+use rubik::zz::{solve_zz, ZzStart};
+use std::env;
+use std::io::{self, BufRead};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("roux") => run_method(&args[1..].join(" "), solve_roux),
+        Some("zz") => run_method(&args[1..].join(" "), |cube| solve_zz(cube, ZzStart::EoLine)),
+        Some("bld") => run_bld(&args[1..].join(" ")),
+        Some("fmc") => run_fmc(&args[1..].join(" ")),
+        Some("gen") if args.len() > 1 => run_restricted(&args[1], &args[2..].join(" ")),
+        Some("optimal") if args.len() > 1 => run_optimal(&args[1], &args[2..].join(" ")),
+        Some("llgen") if args.len() > 1 => run_llgen(&args[1..]),
+        Some("case") => run_case(&args[1..].join(" ")),
+        Some("timer") if args.len() > 1 => run_timer(&args[1], &args[2..]),
+        Some("svg") if args.len() > 1 => run_svg(&args[1], &args[2..].join(" ")),
+        Some("batch") => run_batch_command(&args[1..]),
+        Some("serve") => run_serve(args.get(1).map_or("127.0.0.1:8080", String::as_str)),
+        Some("repl") => run_repl(args.get(1)),
+        Some("play") => run_play(&args[1..].join(" ")),
+        Some("show") => run_show(&args[1..].join(" ")),
+        Some("recon") => run_recon(&args[1..].join(" ")),
+        Some("train") if args.len() > 1 => run_train(&args[1], args.get(2).is_some_and(|arg| arg == "noise")),
+        _ => run_demo(),
+    }
+}
+
+// Scrambled cube from a scramble given in move notation
+fn scrambled_cube(scramble: &str) -> Option<Cube> {
+    let moves = match parse_moves(scramble) {
+        Ok(moves) => moves,
+        Err(e) => {
+            eprintln!("{}", e);
+            return None;
+        }
+    };
+    let mut cube = Cube::new(None).expect("Error initializing cube");
+    apply_moves(&mut cube, &moves);
+    println!("Scramble: {}", format_moves(&moves));
+    Some(cube)
+}
+
+// Solve a scramble given in move notation with a method solver, stage by stage
+fn run_method(scramble: &str, solve: impl Fn(&Cube) -> Result<MethodSolution, String>) {
+    let Some(cube) = scrambled_cube(scramble) else { return };
+    match solve(&cube) {
+        Ok(solution) => {
+            for stage in &solution.stages {
+                println!("{}", stage);
+            }
+            let moves = solution.moves();
+            println!("Total: {} moves: {}", moves.len(), format_moves(&moves));
+        }
+        Err(e) => println!("No solution found: {}", e),
+    }
+}
+
+// Blindfolded memo and execution (UFR / UF buffers, Old Pochmann and M2/R2)
+fn run_bld(scramble: &str) {
+    let Some(cube) = scrambled_cube(scramble) else { return };
+    for (name, execution) in [("Old Pochmann", Execution::OldPochmann), ("M2/R2", Execution::M2R2)] {
+        println!("\n{}:", name);
+        match solve_bld(&cube, Buffers::UfrUf, execution) {
+            Ok(solution) => print!("{}", solution),
+            Err(e) => println!("No solution found: {}", e),
+        }
+    }
+}
+
+// FMC start: EO on every axis (normal and inverse scramble), then DR from the shortest EO
+fn run_fmc(scramble: &str) {
+    let moves = match parse_moves(scramble) {
+        Ok(moves) => moves,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    println!("Scramble: {}", format_moves(&moves));
+    let mut niss = Niss::new(&moves);
+    let eos = match find_all_eo(&niss) {
+        Ok(eos) => eos,
+        Err(e) => {
+            println!("No EO found: {}", e);
+            return;
+        }
+    };
+    for (side, axis, eo) in &eos {
+        println!("EO {:?} ({:?}): {} ({} moves)", axis, side, format_moves(eo), eo.len());
+    }
+
+    let (side, eo_axis, eo) = &eos[0];
+    niss.push(*side, eo);
+    let cube = niss.cube(*side);
+    let best_dr = AXES.iter()
+        .filter(|&&axis| axis != *eo_axis)
+        .filter_map(|&axis| find_dr(&cube, *eo_axis, axis).ok().map(|dr| (axis, dr)))
+        .min_by_key(|(_, dr)| dr.len());
+    match best_dr {
+        Some((axis, dr)) => {
+            println!("DR {:?} ({:?}): {} ({} moves)", axis, side, format_moves(&dr), dr.len());
+            niss.push(*side, &dr);
+            println!("Skeleton: {} ({} moves)", niss, niss.solution().len());
+        }
+        None => println!("No DR found"),
+    }
+}
+
+// Solve a scramble with a restricted move set, e.g. `rubik gen RU R U R' U'` or `rubik gen -B ...`
+fn run_restricted(move_set: &str, scramble: &str) {
+    const MAX_DEPTH: usize = 20;
+    let moves = match parse_move_set(move_set) {
+        Ok(moves) => moves,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let Some(cube) = scrambled_cube(scramble) else { return };
+    match solve_with_moves(&cube, &moves, MAX_DEPTH) {
+        Ok(solution) => println!("{}: {} ({} moves)", format_move_set(&moves), format_moves(&solution), solution.len()),
+        Err(e) => println!("No solution found: {}", e),
+    }
+}
+
+// Every optimal solution in HTM, QTM or STM
+fn run_optimal(metric: &str, scramble: &str) {
+    const MAX_COST: usize = 20;
+    let metric = match metric.to_lowercase().as_str() {
+        "htm" => Metric::Htm,
+        "qtm" => Metric::Qtm,
+        "stm" => Metric::Stm,
+        _ => {
+            eprintln!("Unknown metric: {} (htm, qtm or stm)", metric);
+            return;
+        }
+    };
+    let Some(cube) = scrambled_cube(scramble) else { return };
+    match optimal_solutions(&cube, metric, MAX_COST) {
+        Some(solutions) => {
+            for solution in &solutions {
+                println!("{} ({} {})", format_moves(solution), metric.count(solution), metric);
+            }
+            println!("{} optimal solutions", solutions.len());
+        }
+        None => println!("No solution found within {} {}", MAX_COST, metric),
+    }
+}
+
+// Ranked algorithms for a last layer case: a PLL name or the algorithm it is solved by, then an optional max length
+fn run_llgen(args: &[String]) {
+    const DEFAULT_MAX_LENGTH: usize = 12;
+    const PER_SEARCH: usize = 5;
+    const SHOWN: usize = 10;
+    let (case, max_length) = match args.last().and_then(|arg| arg.parse().ok()) {
+        Some(length) if args.len() > 1 => (args[..args.len() - 1].join(" "), length),
+        _ => (args.join(" "), DEFAULT_MAX_LENGTH),
+    };
+    let cube = match case_cube(&case) {
+        Ok(cube) => cube,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    match generate_algorithms(&cube, &default_move_sets(), max_length, &Ergonomics::default(), PER_SEARCH) {
+        Ok(algorithms) if algorithms.is_empty() => println!("No algorithm within {} moves", max_length),
+        Ok(algorithms) => {
+            for alg in algorithms.iter().take(SHOWN) {
+                println!("{}", alg);
+            }
+        }
+        Err(e) => println!("No algorithm found: {}", e),
+    }
+}
+
+// Case of a scramble in each case set
+fn run_case(scramble: &str) {
+    let Some(cube) = scrambled_cube(scramble) else { return };
+    for set in CaseSet::ALL {
+        match recognize(&cube, set) {
+            Ok(recognition) => match recognition.moves() {
+                Some(moves) => println!("{}: {}", recognition, format_moves(&moves)),
+                None => println!("{}", recognition),
+            },
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+// Drill random cases of one set: set up the scramble, time the solve, then name the case
+fn run_train(set: &str, noise: bool) {
+    const STATS_FILE: &str = "rubik_train.tsv";
+    let Some(set) = CaseSet::from_name(set) else {
+        eprintln!("Unknown case set: {} (OLL, PLL, CMLL, COLL or F2L)", set);
+        return;
+    };
+    let mut stats = match Stats::load(STATS_FILE) {
+        Ok(stats) => stats,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    let mut rng = Rng::from_time();
+    let mut lines = io::stdin().lock().lines();
+    let mut read_line = || lines.next().and_then(Result::ok);
+    loop {
+        let drill = new_drill(set, noise, &mut rng);
+        println!("\nScramble: {}", format_moves(&drill.scramble));
+        println!("Press Enter to start the timer (q to quit)");
+        match read_line() {
+            Some(line) if line.trim() != "q" => {}
+            _ => break,
+        }
+        let start = Instant::now();
+        println!("Press Enter when solved");
+        if read_line().is_none() {
+            break;
+        }
+        let time = start.elapsed();
+        println!("Time: {:.2}s. Which case was it?", time.as_secs_f64());
+        let answer = read_line().unwrap_or_default();
+        let recognized = is_right_answer(drill.case, &answer);
+        match (recognized, &drill.case.algorithm) {
+            (true, _) => println!("Right: {}", drill.case),
+            (false, Some(algorithm)) => println!("Wrong: it was {} ({})", drill.case, format_moves(algorithm)),
+            (false, None) => println!("Wrong: it was {}", drill.case),
+        }
+
+        stats.record(drill.case, time, recognized);
+        if let Err(e) = stats.save(STATS_FILE) {
+            eprintln!("{}", e);
+        }
+    }
+    print!("\n{}", stats.of_set(set));
+}
+
+/*
+Timer sessions stored in a file (csTimer JSON when it ends with .json, plain text otherwise)
+- `stats` (default): averages of each session
+- `add <time> [+2|dnf] [scramble]`: add a solve to the last session
+- `convert <file>`: write the sessions to another file, in the format of its extension
+*/
+fn run_timer(path: &str, args: &[String]) {
+    let loaded = if std::path::Path::new(path).exists() { load_sessions(path) } else { Ok(Vec::new()) };
+    let mut sessions = match loaded {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    let result = match args.first().map(String::as_str) {
+        None | Some("stats") => {
+            sessions.iter().for_each(|session| println!("{}", session));
+            Ok(())
+        }
+        Some("add") if args.len() > 1 => {
+            let (penalty, scramble) = match args.get(2).map(|arg| arg.to_lowercase()).as_deref() {
+                Some("+2") => (Penalty::PlusTwo, &args[3..]),
+                Some("dnf") => (Penalty::Dnf, &args[3..]),
+                _ => (Penalty::None, &args[2..]),
+            };
+            match parse_duration(&args[1]) {
+                Some(time) => {
+                    if sessions.is_empty() {
+                        sessions.push(Session::new("Session 1"));
+                    }
+                    let session = sessions.last_mut().unwrap();
+                    let mut solve = Solve::new(time, penalty, &scramble.join(" "));
+                    solve.timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+                    session.add(solve)
+                        .and_then(|_| save_sessions(path, &sessions))
+                        .map(|_| print!("{}", sessions.last().unwrap()))
+                }
+                None => Err(format!("Invalid time: {}", args[1])),
+            }
+        }
+        Some("convert") if args.len() > 1 => save_sessions(&args[1], &sessions),
+        _ => Err("Usage: rubik timer <file> [stats | add <time> [+2|dnf] [scramble] | convert <file>]".to_string()),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+    }
+}
+
+// Net of a scrambled cube, or "<scramble> / <moves>" to show the cube before and after the moves
+fn run_show(text: &str) {
+    let scheme = match ColorScheme::from_env() {
+        Ok(scheme) => scheme,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let renderer = Renderer::new(scheme, ColorMode::detect());
+    let (scramble, moves) = text.split_once('/').unwrap_or((text, ""));
+    match parse_moves(scramble).and_then(|scramble| Ok((scramble, parse_moves(moves)?))) {
+        Ok((scramble, moves)) => {
+            let mut cube = Cube::new(None).expect("Error initializing cube");
+            apply_moves(&mut cube, &scramble);
+            if moves.is_empty() {
+                print!("{}", renderer.render(&cube));
+            } else {
+                let before = cube;
+                apply_moves(&mut cube, &moves);
+                print!("{}", renderer.render_pair(&before, &cube, &format_moves(&moves)));
+            }
+        }
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+// SVG image of a scramble or of a case ("PLL T"): net, iso (3D view), ll or pll (last layer with arrows)
+fn run_svg(kind: &str, text: &str) {
+    let scheme = match ColorScheme::from_env() {
+        Ok(scheme) => scheme,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let cube = match case_by_id(text) {
+        Some(case) => case.cube(),
+        None => match parse_moves(text) {
+            Ok(moves) => {
+                let mut cube = Cube::new(None).expect("Error initializing cube");
+                apply_moves(&mut cube, &moves);
+                cube
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        },
+    };
+    match kind {
+        "net" => print!("{}", net_svg(&cube, &scheme)),
+        "iso" => print!("{}", isometric_svg(&cube, &scheme)),
+        "ll" => print!("{}", last_layer_svg(&cube, &scheme, false)),
+        "pll" => print!("{}", last_layer_svg(&cube, &scheme, true)),
+        _ => eprintln!("Usage: rubik svg <net|iso|ll|pll> <scramble or case>"),
+    }
+}
+
+// Step through a solution: "<scramble>" plays an optimal IDA* solution, "<scramble> / <solution>" the given one
+fn run_play(text: &str) {
+    const MAX_COST: usize = 20;
+    let (scramble, solution) = text.split_once('/').unwrap_or((text, ""));
+    let Some(cube) = scrambled_cube(scramble) else { return };
+    let moves = if solution.trim().is_empty() {
+        match ida_star_metric(&cube, Metric::Htm, MAX_COST) {
+            Some(solution) => solution,
+            None => {
+                println!("No solution found.");
+                return;
+            }
+        }
+    } else {
+        match parse_moves(solution) {
+            Ok(moves) => moves,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        }
+    };
+    Playback::new(&cube, &moves, Renderer::default()).run(&stdin_lines());
+}
+
+// Interactive cube, or the commands of a script file
+fn run_repl(script: Option<&String>) {
+    let mut repl = Repl::new(Renderer::default());
+    match script {
+        Some(path) => match std::fs::File::open(path) {
+            Ok(file) => repl.run(io::BufReader::new(file), false),
+            Err(e) => eprintln!("Can't read {}: {}", path, e),
+        },
+        None => repl.run(io::stdin().lock(), true),
+    }
+}
+
+// HTTP/JSON solving service on the given address
+fn run_serve(address: &str) {
+    println!("Listening on http://{}", address);
+    if let Err(e) = serve(address, ServerConfig::default()) {
+        eprintln!("{}", e);
+    }
+}
+
+// Options of `rubik batch`, then the input file ("-" or none for stdin)
+fn batch_options(args: &[String]) -> Result<(BatchConfig, Option<String>, Option<String>), String> {
+    let mut config = BatchConfig::default();
+    let (mut input, mut output) = (None, None);
+    let (mut method, mut metric) = ("optimal".to_string(), "htm".to_string());
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--method" => method = value()?,
+            "--metric" => metric = value()?,
+            "--format" => {
+                config.format = match value()?.as_str() {
+                    "jsonl" => BatchFormat::Jsonl,
+                    "csv" => BatchFormat::Csv,
+                    format => return Err(format!("Unknown format: {} (jsonl or csv)", format)),
+                }
+            }
+            "--jobs" => config.jobs = value()?.parse().ok().filter(|&n| n > 0).ok_or("--jobs needs a positive number")?,
+            "--time-limit" => {
+                let ms: u64 = value()?.parse().ok().filter(|&n| n > 0).ok_or("--time-limit needs a positive number of ms")?;
+                config.time_limit = Duration::from_millis(ms);
+            }
+            "--output" => output = Some(value()?),
+            "-" => input = None,
+            arg if arg.starts_with("--") => return Err(format!("Unknown option: {}", arg)),
+            arg => input = Some(arg.to_string()),
+        }
+    }
+    config.method = SolveMethod::parse(&method, &metric)?;
+    Ok((config, input, output))
+}
+
+// Solve one cube per line of a file or stdin, results as JSONL or CSV on stdout or in a file
+fn run_batch_command(args: &[String]) {
+    const USAGE: &str = "Usage: rubik batch [input] [--output file] [--format jsonl|csv] [--method optimal|roux|zz] \
+        [--metric htm|qtm|stm] [--jobs n] [--time-limit ms]";
+    let (config, input, output) = match batch_options(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return;
+        }
+    };
+    let input: Box<dyn BufRead> = match &input {
+        Some(path) => match std::fs::File::open(path) {
+            Ok(file) => Box::new(io::BufReader::new(file)),
+            Err(e) => {
+                eprintln!("Can't read {}: {}", path, e);
+                return;
+            }
+        },
+        None => Box::new(io::stdin().lock()),
+    };
+    let mut output: Box<dyn io::Write> = match &output {
+        Some(path) => match std::fs::File::create(path) {
+            Ok(file) => Box::new(io::BufWriter::new(file)),
+            Err(e) => {
+                eprintln!("Can't write {}: {}", path, e);
+                return;
+            }
+        },
+        None => Box::new(io::stdout().lock()),
+    };
+    match run_batch(input, &mut output, &config) {
+        Ok(summary) => eprintln!(
+            "Solved {}/{} lines in {:.2}s ({} failed)",
+            summary.solved, summary.lines, summary.time.as_secs_f64(), summary.lines - summary.solved
+        ),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+// Reconstruction of a solve: "<scramble> / <solution>", the solution moves may have timestamps (R@120)
+fn run_recon(text: &str) {
+    let Some((scramble, solution)) = text.split_once('/') else {
+        eprintln!("Usage: rubik recon <scramble> / <solution>");
+        return;
+    };
+    match parse_moves(scramble).and_then(|scramble| Ok((scramble, parse_turns(solution)?))) {
+        Ok((scramble, turns)) => {
+            println!("Scramble: {}", format_moves(&scramble));
+            print!("{}", reconstruct(&scramble, turns));
+        }
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+fn run_demo() {
     // 0 - White
     // 1 - Red
     // 2 - Green
@@ -24,22 +520,16 @@ fn main() {
 
 
     // Create a new cube with the initial state
-    let mut cube = Cube::new(Some(& rubik)).expect("Error initializing cube");
+    let cube = Cube::new(Some(& rubik)).expect("Error initializing cube");
     println!("Initial scrambled cube:");
     cube.print(); // Show the initial state of the cube
 
-    // Find the solution using IDA*
+    // Find the solution using IDA* and play it
     if let Some(solution) = ida_star(&cube) {
-        println!("Solution found with {} moves:\n", solution.len());
-
-        for (i, (face, direction)) in solution.iter().enumerate() {
-            println!("Step {}: Rotate {:?} {:?}", i + 1, face, direction);
-            cube.rotate(*face, *direction);
-            cube.print(); // Show the state of the cube after each move
-        }
-
-        println!("Final solved cube:");
-        cube.print();
+        let moves: Vec<Move> = solution.into_iter().map(|(face, direction)| Move::face(face, direction)).collect();
+        let mut playback = Playback::new(&cube, &moves, Renderer::default());
+        playback.playing = true;
+        playback.run(&stdin_lines());
     } else {
         println!("No solution found.");
     }
@@ -0,0 +1,91 @@
+/*!
+* Move tables, built once on first use
+* - Stickers: each of the 27 moves (18 face moves, 9 slice moves) as one 54 entry gather, a
+*   turn in a single pass over the stickers (`Cube::gather`). `Cube::rotate` and
+*   `Cube::rotate_slice` turn with them.
+* - Cubies: each face move as a `CubieCube` to multiply by. Slice moves turn centers, which
+*   a `CubieCube` doesn't have.
+* - Coordinates: for the twist (3^7), flip (2^11) and corner permutation (8!) of a `CubieCube`,
+*   the coordinate after each face move, so a search on coordinates never builds a cube.
+* - Moves are numbered by `Move::index`, face moves first.
+*/
+
+use crate::cube::Cube;
+use crate::cubie::{CubieCube, CORNER_PERM_COUNT, FLIP_COUNT, TWIST_COUNT};
+use crate::notation::{all_moves, Layer, Move};
+use crate::stage::identity;
+use std::sync::OnceLock;
+
+pub const MOVE_COUNT: usize = 27;
+pub const FACE_MOVE_COUNT: usize = 18;
+
+pub type StickerMove = [u8; Cube::CELL_COUNT];
+
+static STICKER_MOVES: OnceLock<[StickerMove; MOVE_COUNT]> = OnceLock::new();
+static CUBIE_MOVES: OnceLock<[CubieCube; FACE_MOVE_COUNT]> = OnceLock::new();
+static COORD_TABLES: OnceLock<CoordTables> = OnceLock::new();
+
+// Sticker i of the turned cube is sticker `table[m.index()][i]` of the cube before the move
+pub fn sticker_moves() -> &'static [StickerMove; MOVE_COUNT] {
+    STICKER_MOVES.get_or_init(|| {
+        let mut tables = [[0; Cube::CELL_COUNT]; MOVE_COUNT];
+        for m in all_moves() {
+            // Turning a cube that holds its own indices shows where each sticker comes from
+            let mut cube = identity();
+            match m.layer {
+                Layer::Face(face) => cube.rotate_strips(face, m.direction),
+                Layer::Slice(slice) => cube.rotate_slice_strips(slice, m.direction),
+            }
+            tables[m.index()] = cube.state;
+        }
+        tables
+    })
+}
+
+// The cubies of the solved cube after each face move, by `Move::index`
+pub fn cubie_moves() -> &'static [CubieCube; FACE_MOVE_COUNT] {
+    CUBIE_MOVES.get_or_init(|| {
+        let mut moves = [CubieCube::solved(); FACE_MOVE_COUNT];
+        for m in all_moves().iter().filter(|m| m.index() < FACE_MOVE_COUNT) {
+            let mut cube = Cube::new(None).expect("Error initializing cube");
+            m.apply(&mut cube);
+            moves[m.index()] = CubieCube::from_cube(&cube).expect("A turned cube has all its pieces");
+        }
+        moves
+    })
+}
+
+// None for slice moves
+pub fn cubie_move(m: &Move) -> Option<&'static CubieCube> {
+    cubie_moves().get(m.index())
+}
+
+/**
+Coordinate after each face move: `twist[c][m]` is the twist after move m from twist c, the same
+for `flip` and `corner_perm`.
+*/
+pub struct CoordTables {
+    pub twist: Vec<[u16; FACE_MOVE_COUNT]>,
+    pub flip: Vec<[u16; FACE_MOVE_COUNT]>,
+    pub corner_perm: Vec<[u16; FACE_MOVE_COUNT]>,
+}
+
+// A coordinate only depends on the pieces it describes, so any cube with that coordinate will do
+fn coord_table(count: u128, set: fn(&mut CubieCube, u16), get: fn(&CubieCube) -> u16) -> Vec<[u16; FACE_MOVE_COUNT]> {
+    let face_moves = cubie_moves();
+    (0..count as u16)
+        .map(|coord| {
+            let mut cubie = CubieCube::solved();
+            set(&mut cubie, coord);
+            std::array::from_fn(|m| get(&cubie.multiply(&face_moves[m])))
+        })
+        .collect()
+}
+
+pub fn coord_tables() -> &'static CoordTables {
+    COORD_TABLES.get_or_init(|| CoordTables {
+        twist: coord_table(TWIST_COUNT, CubieCube::set_twist, CubieCube::twist),
+        flip: coord_table(FLIP_COUNT, CubieCube::set_flip, CubieCube::flip),
+        corner_perm: coord_table(CORNER_PERM_COUNT, CubieCube::set_corner_perm, CubieCube::corner_perm),
+    })
+}
@@ -0,0 +1,246 @@
+/*!
+* Standard move notation: R U R' U2 M' ...
+* - A letter names the layer: U, D, R, L, F, B for faces and M, E, S for slices
+* - No suffix is a clockwise quarter turn, ' is counter-clockwise and 2 is a half turn
+* - A lowercase letter is a wide move, read as the face and the slice next to it: r = R M'
+*/
+
+use crate::cube::{Cube, Face, RotationDirection, Slice};
+use std::fmt;
+
+pub const FACES: [Face; 6] = [Face::U, Face::D, Face::R, Face::L, Face::F, Face::B];
+pub const DIRECTIONS: [RotationDirection; 3] = [
+    RotationDirection::Clockwise,
+    RotationDirection::CounterClockwise,
+    RotationDirection::DoubleTurn,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Layer {
+    Face(Face),
+    Slice(Slice),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub layer: Layer,
+    pub direction: RotationDirection,
+}
+
+impl Move {
+    pub fn face(face: Face, direction: RotationDirection) -> Self {
+        Move { layer: Layer::Face(face), direction }
+    }
+
+    pub fn slice(slice: Slice, direction: RotationDirection) -> Self {
+        Move { layer: Layer::Slice(slice), direction }
+    }
+
+    pub fn inverse(&self) -> Self {
+        Move { layer: self.layer, direction: self.direction.opposite() }
+    }
+
+    // 0 to 26: 3 per layer (clockwise, half turn, counter-clockwise), faces U D R L F B then M E S
+    pub fn index(&self) -> usize {
+        let layer = match self.layer {
+            Layer::Face(face) => face as usize / Cube::FACE_SIZE,
+            Layer::Slice(slice) => FACES.len() + slice as usize,
+        };
+        layer * 3 + self.direction as usize - 1
+    }
+
+    pub fn apply(&self, cube: &mut Cube) {
+        match self.layer {
+            Layer::Face(face) => cube.rotate(face, self.direction),
+            Layer::Slice(slice) => cube.rotate_slice(slice, self.direction),
+        }
+    }
+
+    // Axis of the layer: 0 = U/D/E, 1 = R/L/M, 2 = F/B/S
+    // Moves on the same axis commute with each other
+    pub fn axis(&self) -> usize {
+        match self.layer {
+            Layer::Face(Face::U) | Layer::Face(Face::D) | Layer::Slice(Slice::E) => 0,
+            Layer::Face(Face::R) | Layer::Face(Face::L) | Layer::Slice(Slice::M) => 1,
+            Layer::Face(Face::F) | Layer::Face(Face::B) | Layer::Slice(Slice::S) => 2,
+        }
+    }
+}
+
+impl From<(Face, RotationDirection)> for Move {
+    fn from((face, direction): (Face, RotationDirection)) -> Self {
+        Move::face(face, direction)
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let letter = match self.layer {
+            Layer::Face(face) => format!("{:?}", face),
+            Layer::Slice(slice) => format!("{:?}", slice),
+        };
+        let suffix = match self.direction {
+            RotationDirection::Clockwise => "",
+            RotationDirection::CounterClockwise => "'",
+            RotationDirection::DoubleTurn => "2",
+        };
+        write!(f, "{}{}", letter, suffix)
+    }
+}
+
+/**
+How moves are counted.
+- HTM: every face turn counts 1, a slice turn counts 2 (it is 2 face turns).
+- QTM: quarter turns count 1 and half turns 2, slices count twice that.
+- STM: every face or slice turn counts 1.
+- Custom: any cost per move, it must be at least 1.
+*/
+#[derive(Clone, Copy, Debug)]
+pub enum Metric {
+    Htm,
+    Qtm,
+    Stm,
+    Custom(fn(&Move) -> usize),
+}
+
+impl Metric {
+    pub fn cost(&self, m: &Move) -> usize {
+        let slice = matches!(m.layer, Layer::Slice(_));
+        let half = m.direction == RotationDirection::DoubleTurn;
+        match self {
+            Metric::Htm => if slice { 2 } else { 1 },
+            Metric::Qtm => (if half { 2 } else { 1 }) * (if slice { 2 } else { 1 }),
+            Metric::Stm => 1,
+            Metric::Custom(cost) => cost(m),
+        }
+    }
+
+    // Total cost of a sequence
+    pub fn count(&self, moves: &[Move]) -> usize {
+        moves.iter().map(|m| self.cost(m)).sum()
+    }
+
+    // Moves worth searching with: slice moves only when they count 1 like face moves
+    pub fn moves(&self) -> Vec<Move> {
+        match self {
+            Metric::Htm | Metric::Qtm => face_moves(),
+            Metric::Stm | Metric::Custom(_) => all_moves(),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Metric::Htm => "HTM",
+            Metric::Qtm => "QTM",
+            Metric::Stm => "STM",
+            Metric::Custom(_) => "custom metric",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Every turn (clockwise, counter-clockwise and half turn) of the given layers
+pub fn layer_moves(layers: &[Layer]) -> Vec<Move> {
+    layers.iter()
+        .flat_map(|&layer| DIRECTIONS.iter().map(move |&direction| Move { layer, direction }))
+        .collect()
+}
+
+// The 18 face moves of Cube::MOVES
+pub fn face_moves() -> Vec<Move> {
+    Cube::MOVES.iter().map(|&m| Move::from(m)).collect()
+}
+
+// The 18 face moves and the 9 slice moves
+pub fn all_moves() -> Vec<Move> {
+    let mut moves = face_moves();
+    moves.extend(layer_moves(&[Layer::Slice(Slice::M), Layer::Slice(Slice::E), Layer::Slice(Slice::S)]));
+    moves
+}
+
+// Parse one token like "R", "U'", "M2" or "F2'"
+fn parse_move(token: &str) -> Result<Move, String> {
+    let mut chars = token.chars();
+    let layer = match chars.next() {
+        Some('U') => Layer::Face(Face::U),
+        Some('D') => Layer::Face(Face::D),
+        Some('R') => Layer::Face(Face::R),
+        Some('L') => Layer::Face(Face::L),
+        Some('F') => Layer::Face(Face::F),
+        Some('B') => Layer::Face(Face::B),
+        Some('M') => Layer::Slice(Slice::M),
+        Some('E') => Layer::Slice(Slice::E),
+        Some('S') => Layer::Slice(Slice::S),
+        _ => return Err(format!("Invalid move: {}", token)),
+    };
+    let direction = match chars.as_str() {
+        "" => RotationDirection::Clockwise,
+        "'" | "’" => RotationDirection::CounterClockwise,
+        "2" | "2'" | "2’" => RotationDirection::DoubleTurn,
+        _ => return Err(format!("Invalid move: {}", token)),
+    };
+    Ok(Move { layer, direction })
+}
+
+// Parse one token that may be a wide move: "r" gives R M', "f2" gives F2 S2
+fn parse_token(token: &str) -> Result<Vec<Move>, String> {
+    // The slice turns like the face when it follows it (d = D E), the other way otherwise (r = R M')
+    let (face, slice, same_direction) = match token.chars().next() {
+        Some('u') => ('U', 'E', false),
+        Some('d') => ('D', 'E', true),
+        Some('r') => ('R', 'M', false),
+        Some('l') => ('L', 'M', true),
+        Some('f') => ('F', 'S', true),
+        Some('b') => ('B', 'S', false),
+        _ => return Ok(vec![parse_move(token)?]),
+    };
+    let suffix = &token[1..];
+    let face = parse_move(&format!("{}{}", face, suffix)).map_err(|_| format!("Invalid move: {}", token))?;
+    let slice = parse_move(&format!("{}{}", slice, suffix)).map_err(|_| format!("Invalid move: {}", token))?;
+    Ok(vec![face, if same_direction { slice } else { slice.inverse() }])
+}
+
+// Parse a whitespace separated sequence of moves
+pub fn parse_moves(text: &str) -> Result<Vec<Move>, String> {
+    text.split_whitespace().map(parse_token).collect::<Result<Vec<_>, _>>().map(|moves| moves.concat())
+}
+
+// Format a sequence of moves as "R U R' U'"
+pub fn format_moves(moves: &[Move]) -> String {
+    moves.iter().map(|m| m.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+// The sequence that undoes `moves`
+pub fn invert_moves(moves: &[Move]) -> Vec<Move> {
+    moves.iter().rev().map(|m| m.inverse()).collect()
+}
+
+// Merge consecutive moves of the same layer (R R -> R2, R R' -> nothing)
+pub fn simplify_moves(moves: &[Move]) -> Vec<Move> {
+    let mut result: Vec<Move> = Vec::new();
+    for m in moves {
+        match result.last() {
+            Some(last) if last.layer == m.layer => {
+                let turns = (last.direction as usize + m.direction as usize) % 4;
+                result.pop();
+                match turns {
+                    1 => result.push(Move { layer: m.layer, direction: RotationDirection::Clockwise }),
+                    2 => result.push(Move { layer: m.layer, direction: RotationDirection::DoubleTurn }),
+                    3 => result.push(Move { layer: m.layer, direction: RotationDirection::CounterClockwise }),
+                    _ => {} // The two moves cancel out
+                }
+            }
+            _ => result.push(*m),
+        }
+    }
+    result
+}
+
+// Apply a sequence of moves to the cube
+pub fn apply_moves(cube: &mut Cube, moves: &[Move]) {
+    for m in moves {
+        m.apply(cube);
+    }
+}
@@ -0,0 +1,175 @@
+/*!
+* Step by step playback of a solution in the terminal
+* - Each frame draws the cube after the current step as a net, with the layer of the next move
+*   marked, and the solution with the next move in brackets.
+* - Commands are read one line at a time: Enter or n (next), b (back), g <step> (jump),
+*   p (play / pause), s <seconds> (time per move while playing), q (quit).
+* - While playing, a move is made every `delay` unless a command comes first.
+*/
+
+use crate::cube::Cube;
+use crate::notation::{apply_moves, Layer, Move};
+use crate::render::{ColorMode, Renderer};
+use crate::stage::move_perm;
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+const DEFAULT_DELAY: Duration = Duration::from_millis(800);
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+const HELP: &str = "Enter/n: next, b: back, g <step>: jump, p: play/pause, s <seconds>: speed, q: quit";
+
+pub struct Playback {
+    pub start: Cube,
+    pub moves: Vec<Move>,
+    pub step: usize, // Moves already made
+    pub delay: Duration,
+    pub playing: bool,
+    pub renderer: Renderer,
+}
+
+// Stickers turned by a move, and the center of its face
+fn layer_stickers(m: &Move) -> Vec<usize> {
+    let perm = move_perm(m);
+    let mut stickers: Vec<usize> = (0..Cube::CELL_COUNT).filter(|&i| perm[i] as usize != i).collect();
+    if let Layer::Face(face) = m.layer {
+        stickers.push(face as usize + Cube::FACE_SIZE / 2);
+    }
+    stickers
+}
+
+impl Playback {
+    pub fn new(start: &Cube, moves: &[Move], renderer: Renderer) -> Self {
+        Playback { start: *start, moves: moves.to_vec(), step: 0, delay: DEFAULT_DELAY, playing: false, renderer }
+    }
+
+    // The cube after the current step
+    pub fn cube(&self) -> Cube {
+        let mut cube = self.start;
+        apply_moves(&mut cube, &self.moves[..self.step]);
+        cube
+    }
+
+    pub fn next_move(&self) -> Option<&Move> {
+        self.moves.get(self.step)
+    }
+
+    pub fn forward(&mut self) {
+        self.step = (self.step + 1).min(self.moves.len());
+    }
+
+    pub fn back(&mut self) {
+        self.step = self.step.saturating_sub(1);
+    }
+
+    pub fn jump(&mut self, step: usize) {
+        self.step = step.min(self.moves.len());
+    }
+
+    pub fn frame(&self) -> String {
+        let mut text = String::new();
+        if self.renderer.mode != ColorMode::Plain {
+            text += CLEAR_SCREEN;
+        }
+        text += &format!("Step {}/{}", self.step, self.moves.len());
+        match self.next_move() {
+            Some(m) => text += &format!("   Next move: {}", m),
+            None if self.cube().is_solved() => text += "   Solved",
+            None => text += "   Done",
+        }
+        text += if self.playing { "   (playing)\n" } else { "\n" };
+
+        let solution: Vec<String> = self.moves.iter().enumerate()
+            .map(|(i, m)| if i == self.step { format!("[{}]", m) } else { m.to_string() })
+            .collect();
+        text += &solution.join(" ");
+        text += "\n\n";
+
+        let marked = self.next_move().map(layer_stickers).unwrap_or_default();
+        for line in self.renderer.marked_lines(&self.cube(), &marked) {
+            text += line.trim_end();
+            text += "\n";
+        }
+        text += "\n";
+        text += HELP;
+        text += "\n";
+        text
+    }
+
+    // Apply one command line, false to quit
+    pub fn command(&mut self, line: &str) -> bool {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            None | Some("n") => self.forward(),
+            Some("b") => self.back(),
+            Some("g") => {
+                if let Some(step) = words.next().and_then(|w| w.parse().ok()) {
+                    self.jump(step);
+                }
+            }
+            Some("p") => {
+                // Playing from the last step starts again
+                if !self.playing && self.step == self.moves.len() {
+                    self.step = 0;
+                }
+                self.playing = !self.playing;
+            }
+            Some("s") => {
+                if let Some(seconds) = words.next().and_then(|w| w.parse::<f64>().ok()).filter(|&s| s > 0.0) {
+                    self.delay = Duration::from_secs_f64(seconds);
+                }
+            }
+            Some("q") => return false,
+            Some(_) => {}
+        }
+        true
+    }
+
+    /**
+    Draw frames until the user quits, with the commands from `input` (one line each).
+    - When the input ends, playing goes on to the last step; a paused playback stops.
+    */
+    pub fn run(&mut self, input: &Receiver<String>) {
+        loop {
+            if self.playing && self.step == self.moves.len() {
+                self.playing = false;
+            }
+            print!("{}", self.frame());
+
+            let line = if self.playing {
+                match input.recv_timeout(self.delay) {
+                    Ok(line) => Some(line),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        thread::sleep(self.delay);
+                        None
+                    }
+                }
+            } else {
+                match input.recv() {
+                    Ok(line) => Some(line),
+                    Err(_) => return,
+                }
+            };
+            match line {
+                Some(line) if !self.command(&line) => return,
+                Some(_) => {}
+                None => self.forward(),
+            }
+        }
+    }
+}
+
+// Lines of stdin, read on their own thread so a playing playback doesn't wait for them
+pub fn stdin_lines() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
@@ -0,0 +1,388 @@
+/*!
+* Solve reconstruction analysis (CFOP)
+* - The moves of a solve are replayed on the scrambled cube and the stage boundaries are found on
+*   the colors: cross, each F2L pair, OLL and PLL. The cross may be on any face.
+* - Moves are counted as typed: a wide move counts 1, a rotation (x, y, z) counts 0.
+* - Rotations are replayed by renaming the faces of the moves after them, the cube keeps the
+*   orientation of the scramble.
+* - Moves may carry a timestamp in milliseconds from the start of the solve ("R@1234"), which
+*   gives the time and TPS of every stage.
+* - Moves that cancel (U U', R R) are reported as wasted.
+*/
+
+use crate::cube::{Cube, Face, Slice};
+use crate::notation::{apply_moves, parse_moves, Layer, Move, FACES};
+use std::fmt;
+use std::ops::Range;
+use std::time::Duration;
+
+// Faces in the order the cross is looked for (index of the face in the state array / 9)
+const CROSS_FACES: [usize; 6] = [1, 0, 4, 5, 2, 3]; // D, U, F, B, R, L
+const FACE_NAMES: [&str; 6] = ["U", "D", "R", "L", "F", "B"];
+
+// One move as typed in the solution, a wide move is a face and a slice move, a rotation no move
+#[derive(Clone, Debug)]
+pub struct Turn {
+    pub token: String,
+    pub moves: Vec<Move>,
+    pub time: Option<Duration>, // From the start of the solve
+}
+
+impl Turn {
+    pub fn is_rotation(&self) -> bool {
+        self.moves.is_empty()
+    }
+}
+
+fn move_count(turns: &[Turn]) -> usize {
+    turns.iter().filter(|t| !t.is_rotation()).count()
+}
+
+// Faces (indices into FACES) that take the place of U, F, R... on a clockwise x, y or z:
+// the face at cycle[i + 1] moves to cycle[i]
+fn rotation_cycle(axis: char) -> Option<[usize; 4]> {
+    match axis {
+        'x' => Some([0, 4, 1, 5]), // F to U, D to F, B to D, U to B
+        'y' => Some([3, 4, 2, 5]), // F to L, R to F, B to R, L to B
+        'z' => Some([2, 0, 3, 1]), // U to R, L to U, D to L, R to D
+        _ => None,
+    }
+}
+
+// Rename the faces for a rotation token, None when the token isn't a rotation
+fn rotate_frame(frame: &mut [usize; 6], token: &str) -> Option<Result<(), String>> {
+    let mut chars = token.chars();
+    let cycle = rotation_cycle(chars.next()?)?;
+    let turns = match chars.as_str() {
+        "" => 1,
+        "2" | "2'" | "2’" => 2,
+        "'" | "’" => 3,
+        _ => return Some(Err(format!("Invalid move: {}", token))),
+    };
+    for _ in 0..turns {
+        let old = *frame;
+        for i in 0..4 {
+            frame[cycle[i]] = old[cycle[(i + 1) % 4]];
+        }
+    }
+    Some(Ok(()))
+}
+
+// A move as typed after some rotations, as a move of the cube in the scramble orientation
+fn in_frame(frame: &[usize; 6], m: Move) -> Move {
+    let face = |f: Face| FACES[frame[f as usize / Cube::FACE_SIZE]];
+    match m.layer {
+        Layer::Face(f) => Move::face(face(f), m.direction),
+        // A slice turns like L (M), D (E) or F (S), or the other way as the opposite face
+        Layer::Slice(slice) => {
+            let follows = match slice {
+                Slice::M => Face::L,
+                Slice::E => Face::D,
+                Slice::S => Face::F,
+            };
+            let (slice, same_direction) = match face(follows) {
+                Face::L => (Slice::M, true),
+                Face::R => (Slice::M, false),
+                Face::D => (Slice::E, true),
+                Face::U => (Slice::E, false),
+                Face::F => (Slice::S, true),
+                Face::B => (Slice::S, false),
+            };
+            let m = Move::slice(slice, m.direction);
+            if same_direction { m } else { m.inverse() }
+        }
+    }
+}
+
+/**
+Parse a solution where each move may be followed by a timestamp in milliseconds: "R@120 U@310".
+- Either every move has a timestamp or none has.
+- The moves after a rotation are turned into moves of the cube as it was scrambled.
+*/
+pub fn parse_turns(text: &str) -> Result<Vec<Turn>, String> {
+    let mut frame = [0, 1, 2, 3, 4, 5];
+    let turns = text.split_whitespace().map(|word| {
+        let (token, time) = match word.split_once('@') {
+            Some((token, ms)) => {
+                let ms = ms.parse::<u64>().map_err(|_| format!("Invalid timestamp: {}", word))?;
+                (token, Some(Duration::from_millis(ms)))
+            }
+            None => (word, None),
+        };
+        let moves = match rotate_frame(&mut frame, token) {
+            Some(rotation) => rotation.map(|_| Vec::new())?,
+            None => parse_moves(token)?.into_iter().map(|m| in_frame(&frame, m)).collect(),
+        };
+        Ok(Turn { token: token.to_string(), moves, time })
+    }).collect::<Result<Vec<Turn>, String>>()?;
+
+    if turns.iter().any(|t| t.time.is_some()) && turns.iter().any(|t| t.time.is_none()) {
+        return Err("Either every move or no move must have a timestamp".to_string());
+    }
+    Ok(turns)
+}
+
+// Face of a sticker, and whether the sticker has the color of that face's center
+fn face_of(sticker: usize) -> usize {
+    sticker / Cube::FACE_SIZE
+}
+
+fn is_placed(cube: &Cube, sticker: usize) -> bool {
+    cube.state[sticker] == cube.state[face_of(sticker) * Cube::FACE_SIZE + 4]
+}
+
+fn opposite(face: usize) -> usize {
+    face ^ 1 // U/D, R/L and F/B are next to each other
+}
+
+// Edges around a face, all placed when its cross is solved
+fn cross_edges(face: usize) -> Vec<usize> {
+    (0..12).filter(|&e| Cube::EDGE_FACELETS[e].iter().any(|&s| face_of(s) == face)).collect()
+}
+
+fn is_cross_solved(cube: &Cube, face: usize) -> bool {
+    cross_edges(face).iter().all(|&e| Cube::EDGE_FACELETS[e].iter().all(|&s| is_placed(cube, s)))
+}
+
+// F2L slots of a cross face: the corner on the face and the edge between its two other faces
+fn slots(face: usize) -> Vec<(usize, usize)> {
+    (0..8).filter(|&c| Cube::CORNER_FACELETS[c].iter().any(|&s| face_of(s) == face)).map(|c| {
+        let sides: Vec<usize> = Cube::CORNER_FACELETS[c].iter().map(|&s| face_of(s)).filter(|&f| f != face).collect();
+        let edge = (0..12)
+            .find(|&e| Cube::EDGE_FACELETS[e].iter().all(|&s| sides.contains(&face_of(s))))
+            .expect("Every corner has an edge between its side faces");
+        (c, edge)
+    }).collect()
+}
+
+fn is_slot_solved(cube: &Cube, (corner, edge): (usize, usize)) -> bool {
+    Cube::CORNER_FACELETS[corner].iter().chain(&Cube::EDGE_FACELETS[edge]).all(|&s| is_placed(cube, s))
+}
+
+fn is_face_solved(cube: &Cube, face: usize) -> bool {
+    (0..Cube::FACE_SIZE).all(|i| is_placed(cube, face * Cube::FACE_SIZE + i))
+}
+
+// Moves that cancel each other: turns of the solution merged into one turn or into nothing
+#[derive(Clone, Debug)]
+pub struct Cancellation {
+    pub turns: Vec<usize>, // Indices into the solution
+    pub result: Option<String>,
+}
+
+impl Cancellation {
+    pub fn wasted(&self) -> usize {
+        self.turns.len() - self.result.is_some() as usize
+    }
+}
+
+// Turns of the same layers (R and R', r and r2), merged like `simplify_moves`
+fn find_cancellations(turns: &[Turn]) -> Vec<Cancellation> {
+    struct Merged {
+        turns: Vec<usize>,
+        quarter_turns: usize,
+    }
+    let same_layers = |a: &Turn, b: &Turn| {
+        a.moves.len() == b.moves.len() && a.moves.iter().zip(&b.moves).all(|(x, y)| x.layer == y.layer)
+    };
+
+    let mut cancellations = Vec::new();
+    let mut stack: Vec<Merged> = Vec::new();
+    // Rotations are skipped, the moves around them are already in the same orientation
+    for (i, turn) in turns.iter().enumerate().filter(|(_, turn)| !turn.is_rotation()) {
+        let quarter_turns = turn.moves.first().map_or(0, |m| m.direction as usize);
+        match stack.last_mut() {
+            Some(last) if same_layers(&turns[last.turns[0]], turn) => {
+                last.turns.push(i);
+                last.quarter_turns = (last.quarter_turns + quarter_turns) % 4;
+                if last.quarter_turns == 0 {
+                    let merged = stack.pop().unwrap();
+                    cancellations.push(Cancellation { turns: merged.turns, result: None });
+                }
+            }
+            _ => stack.push(Merged { turns: vec![i], quarter_turns }),
+        }
+    }
+    for merged in stack.into_iter().filter(|merged| merged.turns.len() > 1) {
+        let layer = turns[merged.turns[0]].token.trim_end_matches(['\'', '’', '2']);
+        let result = format!("{}{}", layer, ["", "", "2", "'"][merged.quarter_turns]);
+        cancellations.push(Cancellation { turns: merged.turns, result: Some(result) });
+    }
+    cancellations.sort_by_key(|c| c.turns[0]);
+    cancellations
+}
+
+#[derive(Clone, Debug)]
+pub struct ReconStage {
+    pub name: String,
+    pub turns: Range<usize>, // Indices into the solution
+    pub complete: bool,      // False for the moves after the last stage reached
+    pub moves: usize,        // Turns that aren't rotations
+    pub time: Option<Duration>,
+    pub wasted: usize, // Moves of the stage that cancel
+}
+
+impl ReconStage {
+    pub fn move_count(&self) -> usize {
+        self.moves
+    }
+
+    // Turns per second, when the stage has moves and timestamps
+    pub fn tps(&self) -> Option<f64> {
+        self.time.filter(|t| !t.is_zero() && self.move_count() > 0).map(|t| self.move_count() as f64 / t.as_secs_f64())
+    }
+}
+
+/**
+Reconstruction of a CFOP solve, stage by stage.
+- `cross_face` is the face the cross was built on, None when no cross was ever solved.
+- `solved` is false when the cube isn't solved after the last move.
+*/
+#[derive(Clone, Debug)]
+pub struct Reconstruction {
+    pub turns: Vec<Turn>,
+    pub cross_face: Option<usize>,
+    pub stages: Vec<ReconStage>,
+    pub cancellations: Vec<Cancellation>,
+    pub solved: bool,
+}
+
+impl Reconstruction {
+    pub fn move_count(&self) -> usize {
+        move_count(&self.turns)
+    }
+
+    pub fn wasted(&self) -> usize {
+        self.cancellations.iter().map(Cancellation::wasted).sum()
+    }
+
+    // Time of the last move
+    pub fn time(&self) -> Option<Duration> {
+        self.turns.last().and_then(|t| t.time)
+    }
+
+    pub fn tps(&self) -> Option<f64> {
+        self.time().filter(|t| !t.is_zero()).map(|t| self.move_count() as f64 / t.as_secs_f64())
+    }
+}
+
+// Replay the solution on the scrambled cube and split it into stages
+pub fn reconstruct(scramble: &[Move], turns: Vec<Turn>) -> Reconstruction {
+    let mut cube = Cube::new(None).expect("Error initializing cube");
+    apply_moves(&mut cube, scramble);
+
+    let cancellations = find_cancellations(&turns);
+    let mut stages: Vec<ReconStage> = Vec::new();
+    let push_stage = |stages: &mut Vec<ReconStage>, name: String, end: usize, complete: bool| {
+        let start = stages.last().map_or(0, |s| s.turns.end);
+        let time_at = |end: usize| match end {
+            0 => turns.first().and_then(|t| t.time).map(|_| Duration::ZERO), // Timestamps start with the solve,
+            end => turns[end - 1].time,
+        };
+        let time = time_at(end).zip(time_at(start)).map(|(end, start)| end.saturating_sub(start));
+        let wasted = cancellations.iter()
+            .filter(|c| (start..end).contains(c.turns.last().unwrap()))
+            .map(Cancellation::wasted)
+            .sum();
+        stages.push(ReconStage { name, turns: start..end, complete, moves: move_count(&turns[start..end]), time, wasted });
+    };
+
+    let mut cross_face = None;
+    let mut solved_slots: Vec<usize> = Vec::new();
+    let mut oll_done = false;
+    let mut pll_done = false;
+    // Stages are checked before the first move too, a cross may be solved by the scramble
+    for end in 0..=turns.len() {
+        if end > 0 {
+            apply_moves(&mut cube, &turns[end - 1].moves);
+        }
+        if pll_done {
+            continue;
+        }
+        if cross_face.is_none() {
+            cross_face = CROSS_FACES.iter().copied().find(|&face| is_cross_solved(&cube, face));
+            if let Some(face) = cross_face {
+                push_stage(&mut stages, format!("Cross ({})", FACE_NAMES[face]), end, true);
+            }
+        }
+        let Some(face) = cross_face else { continue };
+        if !is_cross_solved(&cube, face) {
+            continue;
+        }
+        for (i, &slot) in slots(face).iter().enumerate() {
+            if !solved_slots.contains(&i) && is_slot_solved(&cube, slot) {
+                solved_slots.push(i);
+                let name = format!("F2L {} ({})", solved_slots.len(), Cube::EDGE_NAMES[slot.1]);
+                push_stage(&mut stages, name, end, true);
+            }
+        }
+        if solved_slots.len() == 4 && !oll_done && is_face_solved(&cube, opposite(face)) {
+            oll_done = true;
+            push_stage(&mut stages, "OLL".to_string(), end, true);
+        }
+        if oll_done && cube.is_solved() {
+            pll_done = true;
+            push_stage(&mut stages, "PLL".to_string(), end, true);
+        }
+    }
+
+    // Moves after the last stage reached
+    if stages.last().map_or(0, |s| s.turns.end) < turns.len() || !cube.is_solved() {
+        let name = match (cross_face, solved_slots.len()) {
+            _ if pll_done => "Extra".to_string(), // Moves after the solve, the stages are all done
+            (None, _) => "Cross".to_string(),
+            (Some(_), n) if n < 4 => format!("F2L {}", n + 1),
+            _ if !oll_done => "OLL".to_string(),
+            _ => "PLL".to_string(),
+        };
+        push_stage(&mut stages, name, turns.len(), pll_done);
+    }
+
+    let solved = cube.is_solved();
+    Reconstruction { turns, cross_face, stages, cancellations, solved }
+}
+
+impl fmt::Display for Reconstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tokens = |range: Range<usize>| match range.is_empty() {
+            true => "-".to_string(), // Skipped
+            false => self.turns[range].iter().map(|t| t.token.as_str()).collect::<Vec<_>>().join(" "),
+        };
+        for stage in &self.stages {
+            write!(f, "{:<12} ({:>2} moves): {}", stage.name, stage.move_count(), tokens(stage.turns.clone()))?;
+            if let Some(time) = stage.time {
+                write!(f, "  [{:.2}s", time.as_secs_f64())?;
+                if let Some(tps) = stage.tps() {
+                    write!(f, ", {:.2} TPS", tps)?;
+                }
+                write!(f, "]")?;
+            }
+            if stage.wasted > 0 {
+                write!(f, "  ({} wasted)", stage.wasted)?;
+            }
+            if !stage.complete {
+                write!(f, "  (not done)")?;
+            }
+            writeln!(f)?;
+        }
+
+        write!(f, "Total: {} moves", self.move_count())?;
+        if self.wasted() > 0 {
+            write!(f, ", {} after cancellations", self.move_count() - self.wasted())?;
+        }
+        if let (Some(time), Some(tps)) = (self.time(), self.tps()) {
+            write!(f, ", {:.2}s, {:.2} TPS", time.as_secs_f64(), tps)?;
+        }
+        writeln!(f)?;
+
+        for c in &self.cancellations {
+            let turns = c.turns.iter().map(|&i| self.turns[i].token.as_str()).collect::<Vec<_>>().join(" ");
+            let result = c.result.as_deref().unwrap_or("nothing");
+            writeln!(f, "Cancellation: {} -> {} ({} wasted, move {})", turns, result, c.wasted(), c.turns[0] + 1)?;
+        }
+        if !self.solved {
+            writeln!(f, "Warning: the cube is not solved at the end")?;
+        }
+        Ok(())
+    }
+}
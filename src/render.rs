@@ -0,0 +1,193 @@
+/*!
+* Terminal rendering of the cube as an unfolded net
+*           U
+*        L  F  R  B
+*           D
+* - Each sticker is drawn as 2 cells with an ANSI background color, in truecolor or in the
+*   256 color palette. Without colors, each sticker shows the letter of its face in the solved
+*   cube (U, R, F, D, L, B) and unknown stickers show '?'.
+* - The colors come from a color scheme, one color per sticker value (see `Cube`).
+* - Two cubes can be drawn side by side, to show the effect of a sequence of moves.
+* - Stickers can be marked, e.g. the layer about to turn.
+*/
+
+use crate::cube::Cube;
+use std::env;
+use std::io::{self, IsTerminal};
+
+const UNKNOWN_COLOR: [u8; 3] = [96, 96, 96];
+
+// Where each face is drawn in the net, in faces from the top left: (face, row, column)
+const NET: [(usize, usize, usize); 6] = [(0, 0, 1), (3, 1, 0), (4, 1, 1), (2, 1, 2), (5, 1, 3), (1, 2, 1)];
+const FACE_WIDTH: usize = 7; // 3 stickers of 2 cells and a space
+
+const COLOR_NAMES: [(&str, [u8; 3]); 10] = [
+    ("white", [255, 255, 255]),
+    ("yellow", [255, 213, 0]),
+    ("red", [196, 30, 58]),
+    ("orange", [255, 88, 0]),
+    ("green", [0, 158, 96]),
+    ("blue", [0, 81, 186]),
+    ("pink", [255, 105, 180]),
+    ("purple", [128, 0, 128]),
+    ("black", [0, 0, 0]),
+    ("gray", [128, 128, 128]),
+];
+
+/**
+Colors of the 6 sticker values, the default matches `Cube`: white, yellow, red, orange, green
+and blue.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColorScheme {
+    pub colors: [[u8; 3]; Cube::COLOR_COUNT],
+}
+
+impl Default for ColorScheme {
+    fn default() -> Self {
+        let mut colors = [[0; 3]; Cube::COLOR_COUNT];
+        for (color, (_, rgb)) in colors.iter_mut().zip(COLOR_NAMES) {
+            *color = rgb;
+        }
+        ColorScheme { colors }
+    }
+}
+
+impl ColorScheme {
+    // 6 comma separated colors, given by name ("white") or as hex ("#ffd500")
+    pub fn parse(text: &str) -> Result<ColorScheme, String> {
+        let colors: Vec<[u8; 3]> = text.split(',').map(|color| parse_color(color.trim())).collect::<Result<_, _>>()?;
+        let colors = colors.try_into().map_err(|_| format!("A color scheme needs {} colors: {}", Cube::COLOR_COUNT, text))?;
+        Ok(ColorScheme { colors })
+    }
+
+    // The scheme in the RUBIK_COLORS environment variable, the default one when it isn't set
+    pub fn from_env() -> Result<ColorScheme, String> {
+        match env::var("RUBIK_COLORS") {
+            Ok(text) => ColorScheme::parse(&text),
+            Err(_) => Ok(ColorScheme::default()),
+        }
+    }
+
+    pub fn rgb(&self, sticker: u8) -> [u8; 3] {
+        self.colors.get(sticker as usize).copied().unwrap_or(UNKNOWN_COLOR)
+    }
+}
+
+fn parse_color(text: &str) -> Result<[u8; 3], String> {
+    if let Some(hex) = text.strip_prefix('#') {
+        let value = u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6);
+        let value = value.ok_or_else(|| format!("Invalid color: {}", text))?;
+        return Ok([(value >> 16) as u8, (value >> 8) as u8, value as u8]);
+    }
+    COLOR_NAMES.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(text))
+        .map(|&(_, rgb)| rgb)
+        .ok_or_else(|| format!("Invalid color: {}", text))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    TrueColor,
+    Ansi256,
+    Plain, // No escape codes, face letters instead of colors
+}
+
+impl ColorMode {
+    /**
+    The best mode the terminal supports, from the environment:
+    - Output not to a terminal (a file, a pipe), NO_COLOR set, or TERM unset or "dumb": no colors.
+    - COLORTERM "truecolor" or "24bit": truecolor, otherwise the 256 color palette.
+    */
+    pub fn detect() -> ColorMode {
+        let term = env::var("TERM").unwrap_or_default();
+        if !io::stdout().is_terminal() || env::var_os("NO_COLOR").is_some() || term.is_empty() || term == "dumb" {
+            return ColorMode::Plain;
+        }
+        match env::var("COLORTERM").as_deref() {
+            Ok("truecolor") | Ok("24bit") => ColorMode::TrueColor,
+            _ => ColorMode::Ansi256,
+        }
+    }
+}
+
+// Nearest color of the 6x6x6 cube of the 256 color palette
+fn ansi256(rgb: [u8; 3]) -> u8 {
+    let level = |c: u8| ((c as u16 * 5 + 127) / 255) as u8;
+    16 + 36 * level(rgb[0]) + 6 * level(rgb[1]) + level(rgb[2])
+}
+
+#[derive(Clone, Debug)]
+pub struct Renderer {
+    pub scheme: ColorScheme,
+    pub mode: ColorMode,
+}
+
+impl Default for Renderer {
+    // Default colors in the mode the terminal supports
+    fn default() -> Self {
+        Renderer { scheme: ColorScheme::default(), mode: ColorMode::detect() }
+    }
+}
+
+impl Renderer {
+    pub fn new(scheme: ColorScheme, mode: ColorMode) -> Self {
+        Renderer { scheme, mode }
+    }
+
+    // A marked sticker shows "[]" on its color, or a '*' after its letter
+    fn sticker(&self, value: u8, marked: bool) -> String {
+        let rgb = self.scheme.rgb(value);
+        let text = if marked { "\x1b[38;5;16m[]" } else { "  " };
+        match self.mode {
+            ColorMode::TrueColor => format!("\x1b[48;2;{};{};{}m{}\x1b[0m", rgb[0], rgb[1], rgb[2], text),
+            ColorMode::Ansi256 => format!("\x1b[48;5;{}m{}\x1b[0m", ansi256(rgb), text),
+            ColorMode::Plain => format!("{}{}", Cube::FACELET_LETTERS.get(value as usize).unwrap_or(&'?'), if marked { '*' } else { ' ' }),
+        }
+    }
+
+    // The 9 lines of the net, all 4 faces wide
+    pub fn lines(&self, cube: &Cube) -> Vec<String> {
+        self.marked_lines(cube, &[])
+    }
+
+    // The lines of the net with some stickers marked (indices into the state)
+    pub fn marked_lines(&self, cube: &Cube, marked: &[usize]) -> Vec<String> {
+        let mut lines = Vec::new();
+        for net_row in 0..3 {
+            for row in 0..Cube::EDGE_SIZE {
+                let mut line = String::new();
+                for column in 0..4 {
+                    match NET.iter().find(|&&(_, r, c)| r == net_row && c == column) {
+                        Some(&(face, _, _)) => {
+                            let start = face * Cube::FACE_SIZE + row * Cube::EDGE_SIZE;
+                            for i in start..start + Cube::EDGE_SIZE {
+                                line += &self.sticker(cube.state[i], marked.contains(&i));
+                            }
+                            line.push(' ');
+                        }
+                        None => line += &" ".repeat(FACE_WIDTH),
+                    }
+                }
+                lines.push(line);
+            }
+        }
+        lines
+    }
+
+    pub fn render(&self, cube: &Cube) -> String {
+        self.lines(cube).iter().map(|line| line.trim_end().to_string() + "\n").collect()
+    }
+
+    // Two cubes side by side, with a label (the moves from one to the other) between them
+    pub fn render_pair(&self, before: &Cube, after: &Cube, label: &str) -> String {
+        let middle = Cube::EDGE_SIZE + 1; // Line of the label: the middle of the net
+        let arrow = format!(" {} -> ", label);
+        let mut text = String::new();
+        for (i, (left, right)) in self.lines(before).iter().zip(self.lines(after)).enumerate() {
+            let gap = if i == middle { arrow.clone() } else { " ".repeat(arrow.chars().count()) };
+            text += &format!("{}{}{}\n", left, gap, right.trim_end());
+        }
+        text
+    }
+}
@@ -0,0 +1,212 @@
+/*!
+* Interactive cube REPL
+* - A line of moves ("R U R'") turns the cube, other lines are commands: undo, redo, reset,
+*   scramble, solve, show, invert, save, load, history, help and quit.
+* - The cube is kept as the moves that lead to it from the solved cube, so every change can be
+*   undone and the inverse is exact even after slice moves.
+* - The cube is redrawn after each command. Saved cubes only live as long as the REPL.
+* - The same commands can be read from a script, one per line, # starts a comment.
+*/
+
+use crate::cube::Cube;
+use crate::notation::{apply_moves, format_moves, invert_moves, parse_moves, simplify_moves, Metric, Move};
+use crate::render::Renderer;
+use crate::roux::solve_roux;
+use crate::solver::{solve_timed, SolveError, SolveMethod};
+use crate::stage::MethodSolution;
+use crate::train::Rng;
+use crate::zz::{solve_zz, ZzStart};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+const SCRAMBLE_LENGTH: usize = 20;
+const OPTIMAL_TIME_LIMIT: Duration = Duration::from_secs(10);
+const HELP: &str = "\
+<moves>             turn the cube, e.g. R U R' U'
+undo [n], redo [n]  undo or redo the last changes
+reset               back to the solved cube
+scramble [length]   random scramble from the solved cube (20 moves by default)
+solve [method]      solve with roux (default), zz or optimal (10 s limit)
+show                draw the cube and the moves that lead to it
+invert              replace the cube by its inverse
+save <name>         remember the cube
+load [name]         go back to a saved cube, or list them
+history             commands typed so far
+quit                leave";
+
+pub struct Repl {
+    pub moves: Vec<Move>, // From the solved cube to the current one
+    pub cube: Cube,
+    undo: Vec<Vec<Move>>,
+    redo: Vec<Vec<Move>>,
+    saved: BTreeMap<String, Vec<Move>>,
+    pub history: Vec<String>, // Every command run, in order
+    pub renderer: Renderer,
+    rng: Rng,
+}
+
+// What a command asks of the loop
+pub enum Outcome {
+    Continue(String), // Message to show before the cube
+    Quit,
+}
+
+impl Repl {
+    pub fn new(renderer: Renderer) -> Self {
+        Repl {
+            moves: Vec::new(),
+            cube: Cube::new(None).expect("Error initializing cube"),
+            undo: Vec::new(),
+            redo: Vec::new(),
+            saved: BTreeMap::new(),
+            history: Vec::new(),
+            renderer,
+            rng: Rng::from_time(),
+        }
+    }
+
+    fn replay(&mut self, moves: Vec<Move>) {
+        self.cube = Cube::new(None).expect("Error initializing cube");
+        apply_moves(&mut self.cube, &moves);
+        self.moves = moves;
+    }
+
+    // Change the cube, keeping the old one to undo
+    fn set_moves(&mut self, moves: Vec<Move>) {
+        self.undo.push(self.moves.clone());
+        self.redo.clear();
+        self.replay(moves);
+    }
+
+    fn turn(&mut self, moves: &[Move]) {
+        let mut all = self.moves.clone();
+        all.extend(moves);
+        self.set_moves(all);
+    }
+
+    pub fn execute(&mut self, line: &str) -> Result<Outcome, String> {
+        let line = line.trim();
+        if !line.is_empty() {
+            self.history.push(line.to_string());
+        }
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("show");
+        let argument = words.next();
+        let count = |default: usize| argument.map_or(Ok(default), |n| n.parse::<usize>().map_err(|_| format!("Invalid number: {}", n)));
+
+        let message = match command {
+            "undo" | "redo" => {
+                let undo = command == "undo";
+                let steps = count(1)?.min(if undo { self.undo.len() } else { self.redo.len() });
+                for _ in 0..steps {
+                    let (from, to) = if undo { (&mut self.undo, &mut self.redo) } else { (&mut self.redo, &mut self.undo) };
+                    to.push(self.moves.clone());
+                    let moves = from.pop().unwrap();
+                    self.replay(moves);
+                }
+                format!("{} {} step(s)", if undo { "Undid" } else { "Redid" }, steps)
+            }
+            "reset" => {
+                self.set_moves(Vec::new());
+                "Solved cube".to_string()
+            }
+            "scramble" => {
+                let scramble = self.rng.scramble(count(SCRAMBLE_LENGTH)?);
+                self.set_moves(scramble.clone());
+                format!("Scramble: {}", format_moves(&scramble))
+            }
+            "solve" if self.cube.is_solved() => "Already solved".to_string(),
+            "solve" => {
+                let solution = self.solve(argument.unwrap_or("roux"))?;
+                self.turn(&solution);
+                format!("Solution: {} ({} moves)", format_moves(&solution), solution.len())
+            }
+            "show" => format!("Moves: {}", format_moves(&simplify_moves(&self.moves))),
+            "invert" => {
+                self.set_moves(invert_moves(&self.moves));
+                "Inverted".to_string()
+            }
+            "save" => {
+                let name = argument.ok_or("Usage: save <name>")?;
+                self.saved.insert(name.to_string(), self.moves.clone());
+                format!("Saved as {}", name)
+            }
+            "load" => match argument {
+                Some(name) => {
+                    let moves = self.saved.get(name).ok_or_else(|| format!("No cube saved as {}", name))?.clone();
+                    self.set_moves(moves);
+                    format!("Loaded {}", name)
+                }
+                None => format!("Saved: {}", self.saved.keys().cloned().collect::<Vec<_>>().join(", ")),
+            },
+            "history" => self.history.iter().enumerate().map(|(i, line)| format!("{:>4}  {}", i + 1, line)).collect::<Vec<_>>().join("\n"),
+            "help" => HELP.to_string(),
+            "quit" | "exit" => return Ok(Outcome::Quit),
+            _ => {
+                let moves = parse_moves(line).map_err(|e| format!("{} (type help for the commands)", e))?;
+                self.turn(&moves);
+                String::new()
+            }
+        };
+        Ok(Outcome::Continue(message))
+    }
+
+    fn solve(&self, method: &str) -> Result<Vec<Move>, String> {
+        let stages = |solution: Result<MethodSolution, String>| solution.map(|s| s.moves());
+        match method {
+            "roux" => stages(solve_roux(&self.cube)),
+            "zz" => stages(solve_zz(&self.cube, ZzStart::EoLine)),
+            "optimal" => match solve_timed(&self.cube, SolveMethod::Optimal(Metric::Htm), OPTIMAL_TIME_LIMIT) {
+                Ok(solved) => Ok(solved.moves),
+                Err(SolveError::TimedOut { .. }) => {
+                    Err(format!("No solution: time limit reached ({} s)", OPTIMAL_TIME_LIMIT.as_secs()))
+                }
+                Err(e) => Err(e.to_string()),
+            },
+            _ => Err(format!("Unknown method: {} (roux, zz or optimal)", method)),
+        }
+    }
+
+    // The message of a command, then the cube and whether it is solved
+    fn print(&self, message: &str) {
+        if !message.is_empty() {
+            println!("{}", message);
+        }
+        print!("{}", self.renderer.render(&self.cube));
+        if self.cube.is_solved() {
+            println!("Solved");
+        }
+    }
+
+    /**
+    Run commands from `input` until it ends or quit.
+    - With `prompt`, a prompt is shown before each line (interactive use), otherwise each command
+      is echoed (scripts) and errors give their line number.
+    */
+    pub fn run(&mut self, input: impl BufRead, prompt: bool) {
+        if prompt {
+            self.print("Type help for the commands");
+        }
+        let mut lines = input.lines().map_while(Result::ok).enumerate();
+        loop {
+            if prompt {
+                print!("rubik> ");
+                let _ = io::stdout().flush();
+            }
+            let Some((number, line)) = lines.next() else { break };
+            if line.trim_start().starts_with('#') || (!prompt && line.trim().is_empty()) {
+                continue;
+            }
+            if !prompt {
+                println!("> {}", line.trim());
+            }
+            match self.execute(&line) {
+                Ok(Outcome::Continue(message)) => self.print(&message),
+                Ok(Outcome::Quit) => break,
+                Err(e) if prompt => println!("{}", e),
+                Err(e) => println!("Line {}: {}", number + 1, e),
+            }
+        }
+    }
+}
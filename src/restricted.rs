@@ -0,0 +1,100 @@
+/*!
+* Solving with a restricted move set: <R, U> for 2-gen practice, <R, U, F>, or every face but B for
+* a robot without a back gripper.
+* - Before searching, the state is checked against the group generated by the moves, so a state
+*   that can't be reached (a twisted corner with <R, U>, ...) is reported at once.
+* - The search is IDA* with pattern databases of the pieces the moves can reach, built for the move set.
+*/
+
+use crate::cube::{Cube, Face, Piece, RotationDirection};
+use crate::group::PermGroup;
+use crate::notation::{layer_moves, Layer, Move};
+use crate::stage::{identity, invert, reference_stickers, solve_stage, sticker_permutation, LowerBound, PieceTable};
+
+// Largest number of pieces in one pattern database
+const PIECES_PER_TABLE: usize = 4;
+const TABLE_MAX_DEPTH: usize = 12;
+
+// Parse a move set given by its layers: "RU", "R U F", "<R,U,F>" or "-B" for every face but B
+pub fn parse_move_set(text: &str) -> Result<Vec<Move>, String> {
+    let letters: Vec<char> = text.chars().filter(|c| !matches!(c, '<' | '>' | ',' | ' ')).collect();
+    let layer = |letter: char| match letter {
+        'U' => Ok(Layer::Face(Face::U)),
+        'D' => Ok(Layer::Face(Face::D)),
+        'R' => Ok(Layer::Face(Face::R)),
+        'L' => Ok(Layer::Face(Face::L)),
+        'F' => Ok(Layer::Face(Face::F)),
+        'B' => Ok(Layer::Face(Face::B)),
+        _ => Err(format!("Invalid layer: {}", letter)),
+    };
+
+    let layers: Vec<Layer> = match letters.split_first() {
+        Some(('-', excluded)) => {
+            let excluded = excluded.iter().map(|&c| layer(c)).collect::<Result<Vec<_>, _>>()?;
+            "UDRLFB".chars().map(|c| layer(c).unwrap()).filter(|l| !excluded.contains(l)).collect()
+        }
+        _ => letters.iter().map(|&c| layer(c)).collect::<Result<Vec<_>, _>>()?,
+    };
+    // A repeated layer would only add its moves twice to every search
+    let mut unique = Vec::new();
+    for l in layers {
+        if !unique.contains(&l) {
+            unique.push(l);
+        }
+    }
+    if unique.is_empty() {
+        return Err("Empty move set".to_string());
+    }
+    Ok(layer_moves(&unique))
+}
+
+// "<R, U>"
+pub fn format_move_set(moves: &[Move]) -> String {
+    let mut layers: Vec<String> = Vec::new();
+    for m in moves {
+        // The clockwise turn is written with the layer letter only
+        let name = Move { layer: m.layer, direction: RotationDirection::Clockwise }.to_string();
+        if !layers.contains(&name) {
+            layers.push(name);
+        }
+    }
+    format!("<{}>", layers.join(", "))
+}
+
+/**
+Solve the cube with only the given moves, in at most `max_depth` moves.
+- Returns an error when the cube can't be labelled, when the state can't be reached with the move set,
+  or when no solution is found within `max_depth` moves.
+*/
+pub fn solve_with_moves(cube: &Cube, moves: &[Move], max_depth: usize) -> Result<Vec<Move>, String> {
+    let labelled = cube.labelled().ok_or("Invalid cube: pieces can't be identified")?;
+    let group = PermGroup::generated_by(moves);
+    // The labelled cube holds where each sticker comes from, its inverse is where each one went
+    if !group.contains(&invert(&labelled.state)) {
+        return Err(format!("This state can't be reached with {}", format_move_set(moves)));
+    }
+
+    let tables = piece_tables(moves);
+    let bounds: Vec<&dyn LowerBound> = tables.iter().map(|t| t as &dyn LowerBound).collect();
+    let solved = identity();
+    solve_stage(&labelled, moves, &|c| *c == solved, &bounds, max_depth)
+        .ok_or_else(|| format!("No solution within {} moves", max_depth))
+}
+
+// Pattern databases for the corners and the edges the moves can reach, a few pieces per table
+fn piece_tables(moves: &[Move]) -> Vec<PieceTable> {
+    let perms: Vec<_> = moves.iter().map(sticker_permutation).collect();
+    let touched = |stickers: &[usize]| stickers.iter().any(|&s| perms.iter().any(|perm| perm[s] != s));
+
+    let corners: Vec<usize> = (0..Cube::CORNER_FACELETS.len())
+        .filter(|&c| touched(&Cube::piece_stickers(Piece::Corner(c))))
+        .collect();
+    let edges: Vec<usize> = (0..Cube::EDGE_FACELETS.len())
+        .filter(|&e| touched(&Cube::piece_stickers(Piece::Edge(e))))
+        .collect();
+
+    corners.chunks(PIECES_PER_TABLE).map(|chunk| reference_stickers(chunk, &[]))
+        .chain(edges.chunks(PIECES_PER_TABLE).map(|chunk| reference_stickers(&[], chunk)))
+        .map(|stickers| PieceTable::new(&stickers, moves, TABLE_MAX_DEPTH))
+        .collect()
+}
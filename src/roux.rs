@@ -0,0 +1,153 @@
+/*!
+* Roux method solver
+* 1. First block (FB): 1x2x3 block on the left (DL, FL, BL edges, DLF and DBL corners)
+* 2. Second block (SB): 1x2x3 block on the right, built with <R, U, M> so FB stays solved
+* 3. CMLL: the 4 U corners, the case from the CMLL database (see `cases`) with its AUFs
+* 4. LSE: the 6 remaining edges and the M slice centers, using only M and U
+*/
+
+use crate::cases::{solve_case, CaseSet};
+use crate::cube::{Cube, Face, Slice};
+use crate::notation::{face_moves, layer_moves, Layer, Move};
+use crate::stage::*;
+use std::collections::{HashMap, VecDeque};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+// Pieces of each block (indices into Cube::CORNER_FACELETS / Cube::EDGE_FACELETS)
+const FB_CORNERS: [usize; 2] = [5, 6]; // DLF, DBL
+const FB_EDGES: [usize; 3] = [6, 9, 10]; // DL, FL, BL
+const SB_CORNERS: [usize; 2] = [4, 7]; // DFR, DRB
+const SB_EDGES: [usize; 3] = [4, 8, 11]; // DR, FR, BR
+const LSE_EDGES: [usize; 6] = [0, 1, 2, 3, 5, 7]; // UR, UF, UL, UB, DF, DB
+
+const FB_MAX_DEPTH: usize = 12;
+const SB_MAX_DEPTH: usize = 20;
+
+struct RouxTables {
+    fb: [PieceTable; 2],
+    sb: [PieceTable; 2],
+    lse: HashMap<u128, u8>,
+}
+
+static TABLES: OnceLock<RouxTables> = OnceLock::new();
+
+fn tables() -> &'static RouxTables {
+    TABLES.get_or_init(|| {
+        let fb_moves = face_moves();
+        let sb_moves = sb_moves();
+        RouxTables {
+            fb: [
+                PieceTable::new(&reference_stickers(&[], &FB_EDGES), &fb_moves, FB_MAX_DEPTH),
+                PieceTable::new(&reference_stickers(&FB_CORNERS, &FB_EDGES[..1]), &fb_moves, FB_MAX_DEPTH),
+            ],
+            sb: [
+                PieceTable::new(&reference_stickers(&[], &SB_EDGES), &sb_moves, SB_MAX_DEPTH),
+                PieceTable::new(&reference_stickers(&SB_CORNERS, &SB_EDGES[..1]), &sb_moves, SB_MAX_DEPTH),
+            ],
+            lse: generate_lse_table(),
+        }
+    })
+}
+
+// Build the tables now rather than in the first solve
+pub fn preload_tables() {
+    tables();
+}
+
+// <R, U, M>: none of them touch the first block
+fn sb_moves() -> Vec<Move> {
+    layer_moves(&[Layer::Face(Face::R), Layer::Face(Face::U), Layer::Slice(Slice::M)])
+}
+
+// <M, U>: none of them touch the blocks or change the corners relative to each other
+fn lse_moves() -> Vec<Move> {
+    layer_moves(&[Layer::Slice(Slice::M), Layer::Face(Face::U)])
+}
+
+// Stickers that make up the LSE state: the 6 edges, the 4 M slice centers and one U corner sticker for the AUF
+fn lse_key(cube: &Cube) -> u128 {
+    piece_stickers(&[], &LSE_EDGES).iter()
+        .chain([4, 13, 40, 49, 8].iter())
+        .fold(0, |acc, &i| (acc << 6) | cube.state[i] as u128)
+}
+
+// Distance to solved of every LSE state reachable with <M, U>, found by BFS
+fn generate_lse_table() -> HashMap<u128, u8> {
+    let moves = lse_moves();
+    let mut table = HashMap::new();
+    let mut queue = VecDeque::new();
+
+    let solved = identity();
+    table.insert(lse_key(&solved), 0);
+    queue.push_back((solved, 0u8));
+
+    while let Some((cube, depth)) = queue.pop_front() {
+        for m in &moves {
+            let mut next = cube;
+            m.apply(&mut next);
+            table.entry(lse_key(&next)).or_insert_with(|| {
+                queue.push_back((next, depth + 1));
+                depth + 1
+            });
+        }
+    }
+    table
+}
+
+/**
+Solve the cube with the Roux method.
+- Each stage is reported separately with its own moves.
+- Returns an error when the cube can't be labelled or a stage can't be solved.
+*/
+pub fn solve_roux(cube: &Cube) -> Result<MethodSolution, String> {
+    solve_roux_until(cube, None)
+}
+
+// Same as `solve_roux`, the block searches give up at `deadline` (the stage is then not found)
+pub fn solve_roux_until(cube: &Cube, deadline: Option<Instant>) -> Result<MethodSolution, String> {
+    let tables = tables();
+    let mut state = cube.labelled().ok_or("Invalid cube: pieces can't be identified")?;
+    let mut solution = MethodSolution { stages: Vec::new() };
+
+    let fb_goal = piece_stickers(&FB_CORNERS, &FB_EDGES);
+    let fb = solve_stage_until(&state, &face_moves(), &|c| is_home(c, &fb_goal), &[&tables.fb[0], &tables.fb[1]], FB_MAX_DEPTH, deadline)
+        .ok_or("First block not found")?;
+    solution.push_stage(&mut state, "FB", fb);
+
+    let sb_goal = piece_stickers(&SB_CORNERS, &SB_EDGES);
+    let sb = solve_stage_until(&state, &sb_moves(), &|c| is_home(c, &sb_goal), &[&tables.sb[0], &tables.sb[1]], SB_MAX_DEPTH, deadline)
+        .ok_or("Second block not found")?;
+    solution.push_stage(&mut state, "SB", sb);
+
+    let corners = piece_stickers(&[0, 1, 2, 3], &[]);
+    let cmll = solve_case(&state, CaseSet::Cmll, &|c| is_home(c, &corners))?;
+    solution.push_stage(&mut state, "CMLL", cmll);
+
+    let lse = solve_lse(&state, &tables.lse).ok_or("LSE state not reachable with <M, U>")?;
+    solution.push_stage(&mut state, "LSE", lse);
+
+    Ok(solution)
+}
+
+// Follow the LSE table down to distance 0
+fn solve_lse(cube: &Cube, table: &HashMap<u128, u8>) -> Option<Vec<Move>> {
+    let moves = lse_moves();
+    let mut state = *cube;
+    let mut distance = *table.get(&lse_key(&state))?;
+    let mut path = Vec::new();
+
+    while distance > 0 {
+        let (m, next) = moves.iter()
+            .map(|m| {
+                let mut next = state;
+                m.apply(&mut next);
+                (*m, next)
+            })
+            .find(|(_, next)| table.get(&lse_key(next)) == Some(&(distance - 1)))?;
+        path.push(m);
+        state = next;
+        distance -= 1;
+    }
+    Some(path)
+}
@@ -0,0 +1,352 @@
+/*!
+* Local HTTP/JSON solving service (std only)
+* - `GET /scramble?length=20`: random scramble, with the facelets of the scrambled cube.
+* - `POST /validate {"facelets"}`: whether the stickers are a real cube and whether it is solvable.
+* - `POST /apply {"facelets"?, "moves"}`: the facelets after the moves (from the solved cube
+*   when no facelets are given).
+* - `POST /solve {"facelets" | "scramble", "method"?, "metric"?, "time_limit_ms"?}`: solution
+*   with its length and search stats. Methods: roux (default), zz or optimal (HTM, QTM or STM).
+* - Cubes are facelet strings (see `Cube::from_facelets`), moves are in the usual notation.
+* - A fixed pool of workers handles the connections. When its queue is full, new connections get
+*   503 right away.
+* - Each solve has a time limit (see `solver`), reaching it gives 504.
+* - Errors are `{"error": "..."}` with a 4xx or 5xx status.
+*/
+
+use crate::cube::Cube;
+use crate::json::Json;
+use crate::notation::{apply_moves, format_moves, parse_moves, Move};
+use crate::solver::{solve_timed, SolveError, SolveMethod};
+use crate::stage::StageSolution;
+use crate::train::Rng;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const MAX_SCRAMBLE_LENGTH: usize = 100;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    pub workers: usize,
+    pub queue: usize, // Connections waiting for a worker
+    pub time_limit: Duration, // When the request doesn't give one
+    pub max_time_limit: Duration,
+    pub max_body: usize, // In bytes
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            workers: thread::available_parallelism().map_or(4, |n| n.get()),
+            queue: 64,
+            time_limit: Duration::from_secs(5),
+            max_time_limit: Duration::from_secs(60),
+            max_body: 64 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: Json,
+}
+
+impl Response {
+    fn ok(body: Json) -> Self {
+        Response { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Response { status, body: Json::Object(vec![("error".to_string(), Json::from(message))]) }
+    }
+
+    // The same response with one more field in its body
+    fn with(mut self, key: &str, value: Json) -> Self {
+        if let Json::Object(entries) = &mut self.body {
+            entries.push((key.to_string(), value));
+        }
+        self
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Error",
+    }
+}
+
+fn object(entries: Vec<(&str, Json)>) -> Json {
+    Json::Object(entries.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
+
+/**
+Listen on `address` ("127.0.0.1:8080") and serve requests until the process ends.
+- Returns an error when the address can't be bound.
+*/
+pub fn serve(address: &str, config: ServerConfig) -> Result<(), String> {
+    let listener = TcpListener::bind(address).map_err(|e| format!("Can't listen on {}: {}", address, e))?;
+    serve_listener(listener, config)
+}
+
+/**
+Serve requests on a listener that is already bound, e.g. to "127.0.0.1:0" to get a free port
+from `local_addr`.
+*/
+pub fn serve_listener(listener: TcpListener, config: ServerConfig) -> Result<(), String> {
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(config.queue);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..config.workers.max(1) {
+        let receiver = Arc::clone(&receiver);
+        let config = config.clone();
+        thread::spawn(move || worker(&receiver, &config));
+    }
+
+    for stream in listener.incoming().map_while(Result::ok) {
+        match sender.try_send(stream) {
+            Ok(()) => {}
+            Err(TrySendError::Full(mut stream)) => {
+                let _ = write_response(&mut stream, &Response::error(503, "Server busy, try again later"));
+            }
+            Err(TrySendError::Disconnected(_)) => return Err("All workers stopped".to_string()),
+        }
+    }
+    Ok(())
+}
+
+fn worker(receiver: &Mutex<Receiver<TcpStream>>, config: &ServerConfig) {
+    loop {
+        // The lock is only held while waiting for a connection
+        let stream = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+        let Ok(mut stream) = stream else { return };
+        let response = match read_request(&mut stream, config.max_body) {
+            Ok((method, target, body)) => handle(&method, &target, &body, config),
+            Err(response) => response,
+        };
+        let _ = write_response(&mut stream, &response);
+    }
+}
+
+// Method, target and body of a request
+fn read_request(stream: &mut TcpStream, max_body: usize) -> Result<(String, String, String), Response> {
+    let bad_request = |_| Response::error(400, "Invalid HTTP request");
+    stream.set_read_timeout(Some(READ_TIMEOUT)).map_err(bad_request)?;
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(bad_request)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(Response::error(400, "Invalid HTTP request"));
+    };
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(bad_request)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| Response::error(400, "Invalid Content-Length"))?;
+            }
+        }
+    }
+    if content_length > max_body {
+        return Err(Response::error(413, "Request body too large"));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(bad_request)?;
+    let body = String::from_utf8(body).map_err(|_| Response::error(400, "The body must be UTF-8"))?;
+    Ok((method.to_string(), target.to_string(), body))
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> std::io::Result<()> {
+    let body = if response.status == 204 { String::new() } else { response.body.to_string() };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\nConnection: close\r\n\r\n{}",
+        response.status, reason(response.status), body.len(), body
+    )?;
+    stream.flush()
+}
+
+/**
+Answer one request, without any network: the method ("GET"), the target with its query
+("/scramble?length=20") and the body.
+*/
+pub fn handle(method: &str, target: &str, body: &str, config: &ServerConfig) -> Response {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if method == "OPTIONS" {
+        return Response { status: 204, body: Json::Null }; // CORS preflight
+    }
+    let expected = match path {
+        "/scramble" => "GET",
+        "/validate" | "/apply" | "/solve" => "POST",
+        _ => return Response::error(404, &format!("Unknown path: {}", path)),
+    };
+    if method != expected {
+        return Response::error(405, &format!("{} needs {}", path, expected));
+    }
+
+    let request = if method == "POST" {
+        match Json::parse(body) {
+            Ok(request @ Json::Object(_)) => request,
+            Ok(_) => return Response::error(400, "The body must be a JSON object"),
+            Err(e) => return Response::error(400, &e),
+        }
+    } else {
+        Json::Null
+    };
+    let result = match path {
+        "/scramble" => scramble(query),
+        "/validate" => Ok(validate(&request)),
+        "/apply" => apply(&request),
+        _ => solve(&request, config),
+    };
+    result.unwrap_or_else(|response| response)
+}
+
+fn query_value<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    query.split('&').filter_map(|pair| pair.split_once('=')).find(|(k, _)| *k == key).map(|(_, v)| v)
+}
+
+fn string_field<'a>(request: &'a Json, key: &str) -> Result<Option<&'a str>, Response> {
+    match request.get(key) {
+        None | Some(Json::Null) => Ok(None),
+        Some(value) => value.as_str().map(Some).ok_or_else(|| Response::error(400, &format!("{} must be a string", key))),
+    }
+}
+
+fn moves_field(request: &Json, key: &str) -> Result<Option<Vec<Move>>, Response> {
+    string_field(request, key)?
+        .map(|text| parse_moves(text).map_err(|e| Response::error(400, &e)))
+        .transpose()
+}
+
+// The cube of a request: its facelets, or its scramble applied to the solved cube
+fn request_cube(request: &Json) -> Result<Cube, Response> {
+    let mut cube = match string_field(request, "facelets")? {
+        Some(facelets) => Cube::from_facelets(facelets).map_err(|e| Response::error(400, &e))?,
+        None => Cube::new(None).expect("Error initializing cube"),
+    };
+    if let Some(scramble) = moves_field(request, "scramble")? {
+        apply_moves(&mut cube, &scramble);
+    }
+    Ok(cube)
+}
+
+fn scramble(query: &str) -> Result<Response, Response> {
+    let length = match query_value(query, "length") {
+        Some(length) => length.parse::<usize>().ok().filter(|&n| n <= MAX_SCRAMBLE_LENGTH)
+            .ok_or_else(|| Response::error(400, &format!("length must be a number up to {}", MAX_SCRAMBLE_LENGTH)))?,
+        None => 20,
+    };
+    let scramble = Rng::from_time().scramble(length);
+    let mut cube = Cube::new(None).expect("Error initializing cube");
+    apply_moves(&mut cube, &scramble);
+    Ok(Response::ok(object(vec![
+        ("scramble", Json::from(format_moves(&scramble))),
+        ("length", Json::from(scramble.len() as f64)),
+        ("facelets", Json::from(cube.facelets())),
+    ])))
+}
+
+fn validate(request: &Json) -> Response {
+    let facelets = match string_field(request, "facelets") {
+        Ok(Some(facelets)) => facelets,
+        Ok(None) => return Response::error(400, "facelets is required"),
+        Err(response) => return response,
+    };
+    match Cube::from_facelets(facelets) {
+        Ok(cube) => Response::ok(object(vec![
+            ("valid", Json::from(true)),
+            ("solvable", Json::from(cube.is_solvable())),
+            ("solved", Json::from(cube.is_solved())),
+        ])),
+        Err(e) => Response::ok(object(vec![("valid", Json::from(false)), ("error", Json::from(e))])),
+    }
+}
+
+fn apply(request: &Json) -> Result<Response, Response> {
+    let mut cube = request_cube(request)?;
+    let moves = moves_field(request, "moves")?.ok_or_else(|| Response::error(400, "moves is required"))?;
+    apply_moves(&mut cube, &moves);
+    Ok(Response::ok(object(vec![
+        ("facelets", Json::from(cube.facelets())),
+        ("solved", Json::from(cube.is_solved())),
+    ])))
+}
+
+fn solve(request: &Json, config: &ServerConfig) -> Result<Response, Response> {
+    let cube = request_cube(request)?;
+    let time_limit = match request.get("time_limit_ms") {
+        None | Some(Json::Null) => config.time_limit,
+        Some(value) => {
+            let ms = value.as_f64().filter(|ms| *ms > 0.0).ok_or_else(|| Response::error(400, "time_limit_ms must be a positive number"))?;
+            Duration::from_secs_f64(ms / 1000.0).min(config.max_time_limit)
+        }
+    };
+    let method = string_field(request, "method")?.unwrap_or("roux");
+    let metric = string_field(request, "metric")?.unwrap_or("htm");
+    let method = SolveMethod::parse(method, metric).map_err(|e| Response::error(400, &e))?;
+    let stats = |time: Duration, nodes: Option<u64>, timed_out: bool| {
+        let mut stats = vec![
+            ("time_ms", Json::from(time.as_millis() as f64)),
+            ("time_limit_ms", Json::from(time_limit.as_millis() as f64)),
+            ("timed_out", Json::from(timed_out)),
+        ];
+        if let Some(nodes) = nodes {
+            stats.push(("nodes", Json::from(nodes as f64)));
+        }
+        object(stats)
+    };
+
+    match solve_timed(&cube, method, time_limit) {
+        Ok(solved) => {
+            let mut entries = vec![
+                ("method", Json::from(method.name())),
+                ("solution", Json::from(format_moves(&solved.moves))),
+                ("length", Json::from(solved.moves.len() as f64)),
+            ];
+            entries.extend(solved.stages.as_deref().map(|stages| ("stages", stages_json(stages))));
+            entries.push(("stats", stats(solved.time, solved.nodes, false)));
+            if let SolveMethod::Optimal(metric) = method {
+                entries.push(("cost", Json::from(metric.count(&solved.moves) as f64)));
+            }
+            Ok(Response::ok(object(entries)))
+        }
+        Err(e @ SolveError::TimedOut { nodes, time }) => Err(Response::error(504, &e.to_string()).with("stats", stats(time, nodes, true))),
+        Err(e @ SolveError::Crashed) => Err(Response::error(500, &e.to_string())),
+        Err(e) => Err(Response::error(422, &e.to_string())),
+    }
+}
+
+fn stages_json(stages: &[StageSolution]) -> Json {
+    Json::Array(stages.iter().map(|stage| object(vec![
+        ("name", Json::from(stage.name)),
+        ("moves", Json::from(format_moves(&stage.moves))),
+        ("length", Json::from(stage.moves.len() as f64)),
+    ])).collect())
+}
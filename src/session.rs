@@ -0,0 +1,371 @@
+/*!
+* Timer sessions
+* - A solve has its raw time, a penalty (+2 or DNF), the scramble, a comment and when it was done.
+*   Every scramble is checked by applying it to a cube.
+* - Averages follow the WCA rules: the best and worst 5% of the times (rounded up, so 1 each for
+*   ao5 and ao12) are left out and the rest is averaged. A DNF counts as the worst time, and more
+*   DNFs than times left out make the average a DNF. Averages are rounded to the hundredth.
+* - Sessions are imported and exported as csTimer JSON, or as plain text with one solve per line:
+*   "1. 12.34   R U R' ...", "14.34+" for a +2 and "DNF(12.34)".
+*/
+
+use crate::cube::Cube;
+use crate::json::Json;
+use crate::notation::{apply_moves, parse_moves};
+use std::fmt;
+use std::fs;
+use std::time::Duration;
+
+const PLUS_TWO: Duration = Duration::from_secs(2);
+
+// csTimer penalty values
+const CSTIMER_PLUS_TWO: f64 = 2000.0;
+const CSTIMER_DNF: f64 = -1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Penalty {
+    None,
+    PlusTwo,
+    Dnf,
+}
+
+// Result of a solve or an average, a DNF is worse than any time
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Time {
+    Finished(Duration),
+    Dnf,
+}
+
+// "12.34" or "1:02.34", cut to the hundredth
+fn format_duration(time: Duration) -> String {
+    let centis = time.as_millis() / 10;
+    let (minutes, seconds, centis) = (centis / 6000, centis / 100 % 60, centis % 100);
+    if minutes > 0 {
+        format!("{}:{:02}.{:02}", minutes, seconds, centis)
+    } else {
+        format!("{}.{:02}", seconds, centis)
+    }
+}
+
+// Milliseconds rounded to a duration, None when negative, not finite or too large
+pub fn duration_from_millis(millis: f64) -> Option<Duration> {
+    let millis = millis.round();
+    (millis.is_finite() && millis >= 0.0 && millis < u64::MAX as f64).then(|| Duration::from_millis(millis as u64))
+}
+
+// "12.34", "1:02.34" or "12", seconds below 60 after minutes
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let (minutes, seconds, max_seconds) = match text.split_once(':') {
+        Some((minutes, seconds)) => (minutes.parse::<u64>().ok()?, seconds, 60.0),
+        None => (0, text, f64::INFINITY),
+    };
+    let seconds: f64 = seconds.parse().ok()?;
+    if !seconds.is_finite() || seconds < 0.0 || seconds >= max_seconds {
+        return None;
+    }
+    let seconds = duration_from_millis(seconds * 1000.0)?;
+    Duration::from_millis(minutes.checked_mul(60_000)?).checked_add(seconds)
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Time::Finished(time) => write!(f, "{}", format_duration(*time)),
+            Time::Dnf => write!(f, "DNF"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solve {
+    pub time: Duration, // Raw time, without the penalty
+    pub penalty: Penalty,
+    pub scramble: String,
+    pub comment: String,
+    pub timestamp: u64, // Seconds since the Unix epoch, 0 when unknown
+}
+
+impl Solve {
+    pub fn new(time: Duration, penalty: Penalty, scramble: &str) -> Self {
+        Solve { time, penalty, scramble: scramble.to_string(), comment: String::new(), timestamp: 0 }
+    }
+
+    pub fn result(&self) -> Time {
+        match self.penalty {
+            Penalty::None => Time::Finished(self.time),
+            Penalty::PlusTwo => Time::Finished(self.time + PLUS_TWO),
+            Penalty::Dnf => Time::Dnf,
+        }
+    }
+}
+
+// "12.34", "14.34+" or "DNF(12.34)"
+impl fmt::Display for Solve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.penalty {
+            Penalty::None => write!(f, "{}", format_duration(self.time)),
+            Penalty::PlusTwo => write!(f, "{}+", format_duration(self.time + PLUS_TWO)),
+            Penalty::Dnf => write!(f, "DNF({})", format_duration(self.time)),
+        }
+    }
+}
+
+// A scramble must be valid notation, it is applied to a cube to check it
+pub fn validate_scramble(scramble: &str) -> Result<(), String> {
+    let moves = parse_moves(scramble).map_err(|e| format!("Invalid scramble \"{}\": {}", scramble, e))?;
+    let mut cube = Cube::new(None).expect("Error initializing cube");
+    apply_moves(&mut cube, &moves);
+    cube.validate()
+}
+
+/**
+WCA average of some results: the best and worst 5% (rounded up) are left out.
+- Returns None without results, a DNF when there are more DNFs than worst times left out.
+*/
+pub fn average_of(results: &[Time]) -> Option<Time> {
+    if results.is_empty() {
+        return None;
+    }
+    let trim = if results.len() >= 3 { (results.len() * 5).div_ceil(100) } else { 0 };
+    let mut sorted = results.to_vec();
+    sorted.sort();
+    mean_of(&sorted[trim..results.len() - trim])
+}
+
+// Mean of results, a DNF when one of them is, rounded to the hundredth
+pub fn mean_of(results: &[Time]) -> Option<Time> {
+    if results.is_empty() {
+        return None;
+    }
+    let mut total = Duration::ZERO;
+    for result in results {
+        match result {
+            Time::Finished(time) => total += *time,
+            Time::Dnf => return Some(Time::Dnf),
+        }
+    }
+    let millis = total.as_millis() as f64 / results.len() as f64;
+    Some(Time::Finished(Duration::from_millis(((millis / 10.0).round() * 10.0) as u64)))
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Session {
+    pub name: String,
+    pub solves: Vec<Solve>,
+}
+
+impl Session {
+    pub fn new(name: &str) -> Self {
+        Session { name: name.to_string(), solves: Vec::new() }
+    }
+
+    // Add a solve after checking its scramble
+    pub fn add(&mut self, solve: Solve) -> Result<(), String> {
+        validate_scramble(&solve.scramble)?;
+        self.solves.push(solve);
+        Ok(())
+    }
+
+    pub fn results(&self) -> Vec<Time> {
+        self.solves.iter().map(Solve::result).collect()
+    }
+
+    // Mean of the finished solves
+    pub fn mean(&self) -> Option<Time> {
+        let finished: Vec<Time> = self.results().into_iter().filter(|r| *r != Time::Dnf).collect();
+        mean_of(&finished)
+    }
+
+    pub fn best(&self) -> Option<Time> {
+        self.results().into_iter().min()
+    }
+
+    pub fn worst(&self) -> Option<Time> {
+        self.results().into_iter().max()
+    }
+
+    // Average of the last `n` solves
+    pub fn current_average(&self, n: usize) -> Option<Time> {
+        let results = self.results();
+        (n > 0 && results.len() >= n).then(|| average_of(&results[results.len() - n..])).flatten()
+    }
+
+    // Best average of `n` solves in a row
+    pub fn best_average(&self, n: usize) -> Option<Time> {
+        if n == 0 {
+            return None;
+        }
+        self.results().windows(n).filter_map(average_of).min()
+    }
+
+    // One solve per line: "1. 12.34   R U R' ..."
+    pub fn to_text(&self) -> String {
+        self.solves.iter().enumerate()
+            .map(|(i, solve)| format!("{}. {}   {}\n", i + 1, solve, solve.scramble).replace("   \n", "\n"))
+            .collect()
+    }
+
+    // Parse the plain text format, the numbers in front of the times are optional
+    pub fn from_text(name: &str, text: &str) -> Result<Session, String> {
+        let mut session = Session::new(name);
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || format!("Invalid solve on line {}: {}", i + 1, line);
+            let mut tokens = line.split_whitespace().peekable();
+            if tokens.peek().is_some_and(|t| t.ends_with('.') && t[..t.len() - 1].parse::<usize>().is_ok()) {
+                tokens.next();
+            }
+            let time = tokens.next().ok_or_else(invalid)?;
+            let scramble = tokens.collect::<Vec<_>>().join(" ");
+
+            let (time, penalty) = if let Some(raw) = time.strip_prefix("DNF(").and_then(|t| t.strip_suffix(')')) {
+                (parse_duration(raw).ok_or_else(invalid)?, Penalty::Dnf)
+            } else if time == "DNF" {
+                (Duration::ZERO, Penalty::Dnf)
+            } else if let Some(shown) = time.strip_suffix('+') {
+                let shown = parse_duration(shown).ok_or_else(invalid)?;
+                (shown.checked_sub(PLUS_TWO).ok_or_else(invalid)?, Penalty::PlusTwo)
+            } else {
+                (parse_duration(time).ok_or_else(invalid)?, Penalty::None)
+            };
+            session.add(Solve::new(time, penalty, &scramble)).map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        }
+        Ok(session)
+    }
+}
+
+/**
+csTimer export of some sessions.
+- Each solve is [[penalty, time in ms], scramble, comment, timestamp], the penalty is 0, 2000 for
+  +2 or -1 for DNF.
+- The session names are in properties.sessionData, itself a JSON string.
+*/
+pub fn to_cstimer(sessions: &[Session]) -> String {
+    let mut entries: Vec<(String, Json)> = Vec::new();
+    let mut session_data: Vec<(String, Json)> = Vec::new();
+    for (i, session) in sessions.iter().enumerate() {
+        let solves = session.solves.iter()
+            .map(|solve| {
+                let penalty = match solve.penalty {
+                    Penalty::None => 0.0,
+                    Penalty::PlusTwo => CSTIMER_PLUS_TWO,
+                    Penalty::Dnf => CSTIMER_DNF,
+                };
+                Json::Array(vec![
+                    Json::Array(vec![penalty.into(), (solve.time.as_millis() as f64).into()]),
+                    solve.scramble.as_str().into(),
+                    solve.comment.as_str().into(),
+                    (solve.timestamp as f64).into(),
+                ])
+            })
+            .collect();
+        entries.push((format!("session{}", i + 1), Json::Array(solves)));
+        let data = Json::Object(vec![("name".to_string(), session.name.as_str().into()), ("rank".to_string(), ((i + 1) as f64).into())]);
+        session_data.push(((i + 1).to_string(), data));
+    }
+    let properties = Json::Object(vec![("sessionData".to_string(), Json::Object(session_data).to_string().into())]);
+    entries.push(("properties".to_string(), properties));
+    Json::Object(entries).to_string()
+}
+
+// Sessions of a csTimer export, in the order of their numbers
+pub fn from_cstimer(text: &str) -> Result<Vec<Session>, String> {
+    let json = Json::parse(text)?;
+    let entries = json.as_object().ok_or("A csTimer export must be a JSON object")?;
+    let names = json.get("properties")
+        .and_then(|p| p.get("sessionData"))
+        .and_then(Json::as_str)
+        .and_then(|data| Json::parse(data).ok());
+
+    let mut numbered: Vec<(usize, &Json)> = entries.iter()
+        .filter_map(|(key, value)| Some((key.strip_prefix("session")?.parse().ok()?, value)))
+        .collect();
+    numbered.sort_by_key(|(number, _)| *number);
+
+    let mut sessions = Vec::new();
+    for (number, solves) in numbered {
+        let name = names.as_ref()
+            .and_then(|names| names.get(&number.to_string()))
+            .and_then(|data| data.get("name"))
+            .map(|name| match name {
+                Json::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .unwrap_or_else(|| format!("Session {}", number));
+        let mut session = Session::new(&name);
+        for (i, solve) in solves.as_array().ok_or("A csTimer session must be an array")?.iter().enumerate() {
+            let invalid = || format!("Invalid solve {} in session {}", i + 1, number);
+            let fields = solve.as_array().ok_or_else(invalid)?;
+            let time = fields.first().and_then(Json::as_array).ok_or_else(invalid)?;
+            let penalty = match time.first().and_then(Json::as_f64).ok_or_else(invalid)? {
+                p if p == CSTIMER_DNF => Penalty::Dnf,
+                0.0 => Penalty::None,
+                p if p == CSTIMER_PLUS_TWO => Penalty::PlusTwo,
+                _ => return Err(invalid()),
+            };
+            let time = time.get(1).and_then(Json::as_f64).and_then(duration_from_millis).ok_or_else(invalid)?;
+            let text = |k: usize| fields.get(k).and_then(Json::as_str).unwrap_or("").to_string();
+            let solve = Solve {
+                time,
+                penalty,
+                scramble: text(1),
+                comment: text(2),
+                timestamp: fields.get(3).and_then(Json::as_f64).unwrap_or(0.0) as u64,
+            };
+            session.add(solve).map_err(|e| format!("Session {}, solve {}: {}", number, i + 1, e))?;
+        }
+        sessions.push(session);
+    }
+    Ok(sessions)
+}
+
+// Sessions of a file: csTimer JSON for .json files, plain text otherwise (one session per "# name" header)
+pub fn load_sessions(path: &str) -> Result<Vec<Session>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Can't read {}: {}", path, e))?;
+    if path.ends_with(".json") {
+        return from_cstimer(&text);
+    }
+
+    // Every header starts a session, even an empty one; solves before the first header are "Session 1"
+    let mut sessions = Vec::new();
+    let mut name: Option<String> = None;
+    let mut lines = String::new();
+    for line in text.lines().chain(["# end"]) {
+        if let Some(header) = line.strip_prefix("# ") {
+            if name.is_some() || !lines.trim().is_empty() {
+                sessions.push(Session::from_text(name.as_deref().unwrap_or("Session 1"), &lines)?);
+                lines.clear();
+            }
+            name = Some(header.trim().to_string());
+        } else {
+            lines += line;
+            lines += "\n";
+        }
+    }
+    Ok(sessions)
+}
+
+pub fn save_sessions(path: &str, sessions: &[Session]) -> Result<(), String> {
+    let text = if path.ends_with(".json") {
+        to_cstimer(sessions)
+    } else {
+        sessions.iter().map(|s| format!("# {}\n{}", s.name, s.to_text())).collect()
+    };
+    fs::write(path, text).map_err(|e| format!("Can't write {}: {}", path, e))
+}
+
+// Summary: count, mean, best, worst and the current and best averages
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let show = |time: Option<Time>| time.map_or("-".to_string(), |t| t.to_string());
+        writeln!(f, "{}: {} solves, mean {}", self.name, self.solves.len(), show(self.mean()))?;
+        writeln!(f, "best {}, worst {}", show(self.best()), show(self.worst()))?;
+        for n in [5, 12, 100] {
+            writeln!(f, "ao{}: current {}, best {}", n, show(self.current_average(n)), show(self.best_average(n)))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+/*!
+* Solving with a time limit, whatever the method
+* - Roux, ZZ (EOLine start) or an optimal IDA* search in HTM, QTM or STM.
+* - Every solver stops at the time limit and runs on the caller's thread (building the tables,
+*   once, doesn't count). A method solver checks the limit in its block and F2L searches, the
+*   last layer stages are quick.
+* - Shared by the HTTP service, the C API and batch solving.
+*/
+
+use crate::cube::Cube;
+use crate::ida_star::{self, solutions};
+use crate::notation::{Metric, Move};
+use crate::roux::{self, solve_roux_until};
+use crate::stage::StageSolution;
+use crate::zz::{self, solve_zz_until, ZzStart};
+use std::fmt;
+use std::panic;
+use std::time::{Duration, Instant};
+
+pub const OPTIMAL_MAX_COST: usize = 20;
+
+#[derive(Clone, Copy, Debug)]
+pub enum SolveMethod {
+    Roux,
+    Zz,
+    Optimal(Metric),
+}
+
+impl SolveMethod {
+    // "roux", "zz" or "optimal", the metric ("htm", "qtm" or "stm") only matters for optimal
+    pub fn parse(method: &str, metric: &str) -> Result<SolveMethod, String> {
+        match method {
+            "roux" => Ok(SolveMethod::Roux),
+            "zz" => Ok(SolveMethod::Zz),
+            "optimal" => match metric {
+                "htm" => Ok(SolveMethod::Optimal(Metric::Htm)),
+                "qtm" => Ok(SolveMethod::Optimal(Metric::Qtm)),
+                "stm" => Ok(SolveMethod::Optimal(Metric::Stm)),
+                _ => Err(format!("Unknown metric: {} (htm, qtm or stm)", metric)),
+            },
+            _ => Err(format!("Unknown method: {} (roux, zz or optimal)", method)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SolveMethod::Roux => "roux",
+            SolveMethod::Zz => "zz",
+            SolveMethod::Optimal(_) => "optimal",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Solved {
+    pub moves: Vec<Move>,
+    pub stages: Option<Vec<StageSolution>>, // For the methods
+    pub nodes: Option<u64>, // For the optimal search
+    pub time: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SolveError {
+    Unsolvable, // No moves can solve the cube
+    NoSolution(String), // The solver gave up
+    TimedOut { nodes: Option<u64>, time: Duration },
+    Crashed, // The solver panicked
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolveError::Unsolvable => write!(f, "The cube can't be solved"),
+            SolveError::NoSolution(reason) => write!(f, "{}", reason),
+            SolveError::TimedOut { .. } => write!(f, "Time limit reached before a solution was found"),
+            SolveError::Crashed => write!(f, "The solver failed"),
+        }
+    }
+}
+
+// Build the tables of the method, so that no solve (and no time limit) pays for them
+pub fn preload(method: SolveMethod) {
+    match method {
+        SolveMethod::Roux => roux::preload_tables(),
+        SolveMethod::Zz => zz::preload_tables(),
+        SolveMethod::Optimal(metric) => ida_star::preload_tables(metric),
+    }
+}
+
+pub fn solve_timed(cube: &Cube, method: SolveMethod, time_limit: Duration) -> Result<Solved, SolveError> {
+    if !cube.is_solvable() {
+        return Err(SolveError::Unsolvable);
+    }
+    match method {
+        SolveMethod::Optimal(metric) => {
+            let search = solutions(cube, metric, OPTIMAL_MAX_COST).ok_or(SolveError::Unsolvable)?;
+            let start = Instant::now();
+            let mut search = search.with_deadline(start + time_limit);
+            match search.next() {
+                Some(moves) => Ok(Solved { moves, stages: None, nodes: Some(search.nodes()), time: start.elapsed() }),
+                None if search.timed_out() => Err(SolveError::TimedOut { nodes: Some(search.nodes()), time: start.elapsed() }),
+                None => Err(SolveError::NoSolution(format!("No solution within {} {}", OPTIMAL_MAX_COST, metric))),
+            }
+        }
+        SolveMethod::Roux | SolveMethod::Zz => {
+            preload(method);
+            let start = Instant::now();
+            let deadline = start + time_limit;
+            let solution = panic::catch_unwind(|| match method {
+                SolveMethod::Roux => solve_roux_until(cube, Some(deadline)),
+                _ => solve_zz_until(cube, ZzStart::EoLine, Some(deadline)),
+            });
+            match solution {
+                Ok(Ok(solution)) => Ok(Solved { moves: solution.moves(), stages: Some(solution.stages), nodes: None, time: start.elapsed() }),
+                // A stage search that gave up at the deadline isn't a stage without solution
+                Ok(Err(_)) if Instant::now() >= deadline => Err(SolveError::TimedOut { nodes: None, time: start.elapsed() }),
+                Ok(Err(e)) => Err(SolveError::NoSolution(e)),
+                Err(_) => Err(SolveError::Crashed),
+            }
+        }
+    }
+}
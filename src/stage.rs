@@ -0,0 +1,543 @@
+/*!
+* Tools shared by the method solvers (Roux, ...), which solve the cube one stage at a time.
+* - A stage works on a labelled cube (see `Cube::labelled`): the sticker that belongs at
+*   position i is solved when `state[i] == i`.
+* - A stage is solved with IDA* using a restricted move set and pattern database lower bounds,
+*   or by combining known algorithms.
+*/
+
+use crate::cube::Cube;
+use crate::notation::{format_moves, parse_moves, simplify_moves, Layer, Metric, Move};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+// Nodes between two looks at the deadline of `Solutions`
+const DEADLINE_CHECK_NODES: u64 = 4096;
+
+// Moves found for one stage of a method
+#[derive(Clone, Debug)]
+pub struct StageSolution {
+    pub name: &'static str,
+    pub moves: Vec<Move>,
+}
+
+impl fmt::Display for StageSolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:<6} ({:>2} moves): {}", self.name, self.moves.len(), format_moves(&self.moves))
+    }
+}
+
+// Full solution of a method, stage by stage
+#[derive(Clone, Debug)]
+pub struct MethodSolution {
+    pub stages: Vec<StageSolution>,
+}
+
+impl MethodSolution {
+    // All the stages, with the moves that meet at stage boundaries merged or cancelled
+    pub fn moves(&self) -> Vec<Move> {
+        let moves: Vec<Move> = self.stages.iter().flat_map(|s| s.moves.iter().copied()).collect();
+        simplify_moves(&moves)
+    }
+
+    pub fn move_count(&self) -> usize {
+        self.moves().len()
+    }
+
+    // Apply the moves of a stage to the labelled cube and record the stage
+    pub fn push_stage(&mut self, state: &mut Cube, name: &'static str, moves: Vec<Move>) {
+        for m in &moves {
+            m.apply(state);
+        }
+        self.stages.push(StageSolution { name, moves });
+    }
+}
+
+// Where every sticker goes after `m`: the sticker at position p ends up at `permutation(m)[p]`
+pub fn sticker_permutation(m: &Move) -> [usize; Cube::CELL_COUNT] {
+    let mut cube = identity();
+    m.apply(&mut cube);
+    let mut perm = [0; Cube::CELL_COUNT];
+    for (to, &from) in cube.state.iter().enumerate() {
+        perm[from as usize] = to;
+    }
+    perm
+}
+
+// The solved labelled cube: every sticker holds its own index
+pub fn identity() -> Cube {
+    let mut state = [0; Cube::CELL_COUNT];
+    for (i, val) in state.iter_mut().enumerate() {
+        *val = i as u8;
+    }
+    Cube { state }
+}
+
+static ROTATIONS: OnceLock<Vec<Cube>> = OnceLock::new();
+
+// The solved labelled cube in each of its 24 orientations, the identity first
+pub fn rotations() -> &'static [Cube] {
+    ROTATIONS.get_or_init(|| {
+        // x and y as a face and slice turns, every rotation is a product of them
+        let generators: Vec<Cube> = ["R M' L'", "U E' D'"].iter()
+            .map(|alg| {
+                let mut cube = identity();
+                parse_moves(alg).expect("Invalid rotation").iter().for_each(|m| m.apply(&mut cube));
+                cube
+            })
+            .collect();
+        let mut rotations = vec![identity()];
+        let mut i = 0;
+        while i < rotations.len() {
+            for generator in &generators {
+                let mut next = rotations[i];
+                next.gather(&generator.state);
+                if !rotations.contains(&next) {
+                    rotations.push(next);
+                }
+            }
+            i += 1;
+        }
+        rotations
+    })
+}
+
+// Sticker permutation of a whole algorithm: the sticker at position p goes to `perm[p]`
+pub type Perm = [u8; Cube::CELL_COUNT];
+
+pub fn identity_perm() -> Perm {
+    identity().state
+}
+
+// Apply `a` then `b`
+pub fn compose(a: &Perm, b: &Perm) -> Perm {
+    let mut c = [0; Cube::CELL_COUNT];
+    for p in 0..Cube::CELL_COUNT {
+        c[p] = b[a[p] as usize];
+    }
+    c
+}
+
+pub fn invert(a: &Perm) -> Perm {
+    let mut inv = [0; Cube::CELL_COUNT];
+    for (p, &q) in a.iter().enumerate() {
+        inv[q as usize] = p as u8;
+    }
+    inv
+}
+
+pub fn move_perm(m: &Move) -> Perm {
+    sticker_permutation(m).map(|p| p as u8)
+}
+
+pub fn alg_perm(moves: &[Move]) -> Perm {
+    moves.iter().fold(identity_perm(), |acc, m| compose(&acc, &move_perm(m)))
+}
+
+// Every move with its permutation, computed once before a search
+pub fn move_perms(moves: &[Move]) -> Vec<(Move, Perm)> {
+    moves.iter().map(|m| (*m, move_perm(m))).collect()
+}
+
+// Visit every move sequence of exactly `depth` moves, without redundant moves
+pub fn for_each_sequence(depth: usize, moves: &[(Move, Perm)], path: &mut Vec<Move>, perm: &Perm, visit: &mut dyn FnMut(&[Move], &Perm)) {
+    if path.len() == depth {
+        visit(path, perm);
+        return;
+    }
+    for (m, m_perm) in moves {
+        if is_redundant(path.last(), m) {
+            continue;
+        }
+        path.push(*m);
+        let next = compose(perm, m_perm);
+        for_each_sequence(depth, moves, path, &next, visit);
+        path.pop();
+    }
+}
+
+// Lower bound of the number of moves needed to finish a stage
+pub trait LowerBound {
+    fn estimate(&self, cube: &Cube) -> usize;
+}
+
+/**
+Pattern database for a small set of pieces.
+- Each piece is tracked by one reference sticker, its position gives both place and orientation.
+- The table is filled by BFS from the solved state with the stage's move set, so the stored
+  distance is a lower bound of the moves needed to solve these pieces.
+*/
+pub struct PieceTable {
+    stickers: Vec<usize>,
+    distance: HashMap<u64, u8>,
+    max_depth: usize,
+}
+
+impl PieceTable {
+    pub fn new(stickers: &[usize], moves: &[Move], max_depth: usize) -> Self {
+        // Every move costs 1, whatever its layer
+        Self::with_metric(stickers, moves, Metric::Stm, max_depth)
+    }
+
+    /**
+    Same table where each move costs what the metric says, so the distances stay lower bounds of
+    the cost in that metric. Filled by a shortest path search with one queue per cost.
+    */
+    pub fn with_metric(stickers: &[usize], moves: &[Move], metric: Metric, max_cost: usize) -> Self {
+        Self::fill(stickers, &[stickers.to_vec()], moves, metric, max_cost)
+    }
+
+    /**
+    Table of the distance to the nearest of several labelled goal cubes (e.g. the solved cube after
+    each U turn), a lower bound of the moves needed to reach any of them.
+    */
+    pub fn with_goals(stickers: &[usize], goals: &[Cube], moves: &[Move], max_depth: usize) -> Self {
+        let starts: Vec<Vec<usize>> = goals.iter().map(|goal| stickers.iter().map(|&s| goal.locate(s)).collect()).collect();
+        Self::fill(stickers, &starts, moves, Metric::Stm, max_depth)
+    }
+
+    fn fill(stickers: &[usize], starts: &[Vec<usize>], moves: &[Move], metric: Metric, max_cost: usize) -> Self {
+        let perms: Vec<_> = moves.iter().map(|m| (sticker_permutation(m), metric.cost(m))).collect();
+        let mut distance = HashMap::new();
+        let mut queues: Vec<Vec<Vec<usize>>> = vec![Vec::new(); max_cost + 1];
+
+        for start in starts {
+            distance.insert(Self::key(start), 0);
+            queues[0].push(start.clone());
+        }
+
+        for cost in 0..max_cost {
+            while let Some(positions) = queues[cost].pop() {
+                if distance[&Self::key(&positions)] as usize != cost {
+                    continue; // Already reached with a smaller cost
+                }
+                for (perm, move_cost) in &perms {
+                    let next_cost = cost + move_cost;
+                    if next_cost > max_cost {
+                        continue;
+                    }
+                    let next: Vec<usize> = positions.iter().map(|&p| perm[p]).collect();
+                    let known = distance.entry(Self::key(&next)).or_insert(u8::MAX);
+                    if next_cost < *known as usize {
+                        *known = next_cost as u8;
+                        queues[next_cost].push(next);
+                    }
+                }
+            }
+        }
+
+        PieceTable { stickers: stickers.to_vec(), distance, max_depth: max_cost }
+    }
+
+    // Same as `estimate` from the positions of every label, to share them between tables
+    pub fn estimate_at(&self, position: &[usize; Cube::CELL_COUNT]) -> usize {
+        let key = self.stickers.iter().fold(0, |acc, &s| (acc << 6) | position[s] as u64);
+        match self.distance.get(&key) {
+            Some(&d) => d as usize,
+            None => self.max_depth + 1,
+        }
+    }
+
+    // 6 bits per sticker position
+    fn key(positions: &[usize]) -> u64 {
+        positions.iter().fold(0, |acc, &p| (acc << 6) | p as u64)
+    }
+}
+
+impl LowerBound for PieceTable {
+    // Lower bound of the moves needed to solve the tracked pieces of a labelled cube
+    fn estimate(&self, cube: &Cube) -> usize {
+        self.estimate_at(&positions(cube))
+    }
+}
+
+// Position of every label of a labelled cube
+pub fn positions(cube: &Cube) -> [usize; Cube::CELL_COUNT] {
+    let mut position = [0; Cube::CELL_COUNT];
+    for (i, &label) in cube.state.iter().enumerate() {
+        position[label as usize] = i;
+    }
+    position
+}
+
+/**
+Pattern database for any coordinate of a labelled cube (edge orientation, ...).
+- `key` must be a real coordinate: the key after a move only depends on the key before it.
+- The table is filled by BFS from the solved state, keeping one cube per key.
+*/
+pub struct CoordTable {
+    key: fn(&Cube) -> u64,
+    distance: HashMap<u64, u8>,
+    max_depth: usize,
+}
+
+impl CoordTable {
+    pub fn new(key: fn(&Cube) -> u64, moves: &[Move], max_depth: usize) -> Self {
+        let mut distance = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        let solved = identity();
+        distance.insert(key(&solved), 0);
+        queue.push_back((solved, 0));
+
+        while let Some((cube, depth)) = queue.pop_front() {
+            if depth >= max_depth {
+                continue;
+            }
+            for m in moves {
+                let mut next = cube;
+                m.apply(&mut next);
+                distance.entry(key(&next)).or_insert_with(|| {
+                    queue.push_back((next, depth + 1));
+                    depth as u8 + 1
+                });
+            }
+        }
+
+        CoordTable { key, distance, max_depth }
+    }
+}
+
+impl LowerBound for CoordTable {
+    fn estimate(&self, cube: &Cube) -> usize {
+        match self.distance.get(&(self.key)(cube)) {
+            Some(&d) => d as usize,
+            None => self.max_depth + 1,
+        }
+    }
+}
+
+// True when every sticker in `goal` is at its home position
+pub fn is_home(cube: &Cube, goal: &[usize]) -> bool {
+    goal.iter().all(|&i| cube.state[i] as usize == i)
+}
+
+// All the stickers of the given corners and edges (indices into CORNER_FACELETS / EDGE_FACELETS)
+pub fn piece_stickers(corners: &[usize], edges: &[usize]) -> Vec<usize> {
+    corners.iter().flat_map(|&c| Cube::CORNER_FACELETS[c].to_vec())
+        .chain(edges.iter().flat_map(|&e| Cube::EDGE_FACELETS[e].to_vec()))
+        .collect()
+}
+
+// One reference sticker per piece, used to build a PieceTable
+pub fn reference_stickers(corners: &[usize], edges: &[usize]) -> Vec<usize> {
+    corners.iter().map(|&c| Cube::CORNER_FACELETS[c][0])
+        .chain(edges.iter().map(|&e| Cube::EDGE_FACELETS[e][0]))
+        .collect()
+}
+
+// Order of the layers on one axis, used to keep only one order of two commuting moves
+fn layer_rank(layer: Layer) -> usize {
+    match layer {
+        Layer::Face(face) => face as usize,
+        Layer::Slice(slice) => 100 + slice as usize,
+    }
+}
+
+// Skip moves that are redundant after `last`: the same layer again, or a commuting move in the wrong order
+pub fn is_redundant(last: Option<&Move>, next: &Move) -> bool {
+    match last {
+        None => false,
+        Some(last) => {
+            last.layer == next.layer
+                || (last.axis() == next.axis() && layer_rank(last.layer) > layer_rank(next.layer))
+        }
+    }
+}
+
+/**
+Solve one stage with IDA*.
+- `cube` is a labelled cube.
+- The stage is done when `goal` returns true.
+- `tables` give the lower bound, the largest one is used.
+- Returns None when nothing is found within `max_depth` moves.
+*/
+pub fn solve_stage(
+    cube: &Cube,
+    moves: &[Move],
+    goal: &dyn Fn(&Cube) -> bool,
+    tables: &[&dyn LowerBound],
+    max_depth: usize,
+) -> Option<Vec<Move>> {
+    solve_stage_metric(cube, moves, Metric::Stm, goal, tables, max_depth)
+}
+
+// Same as `solve_stage`, giving up (None) at `deadline`
+pub fn solve_stage_until(
+    cube: &Cube,
+    moves: &[Move],
+    goal: &dyn Fn(&Cube) -> bool,
+    tables: &[&dyn LowerBound],
+    max_depth: usize,
+    deadline: Option<Instant>,
+) -> Option<Vec<Move>> {
+    let estimate = |c: &Cube| tables.iter().map(|t| t.estimate(c)).max().unwrap_or(0);
+    let mut search = Solutions::new(cube, moves, Metric::Stm, Box::new(goal), Box::new(estimate), max_depth);
+    if let Some(deadline) = deadline {
+        search = search.with_deadline(deadline);
+    }
+    search.next()
+}
+
+/**
+Same as `solve_stage` where a path costs the sum of its moves in `metric`.
+- The tables must be lower bounds in the same metric (`PieceTable::with_metric`), or the
+  solution may not be optimal.
+- Returns None when nothing is found within a cost of `max_cost`.
+*/
+pub fn solve_stage_metric(
+    cube: &Cube,
+    moves: &[Move],
+    metric: Metric,
+    goal: &dyn Fn(&Cube) -> bool,
+    tables: &[&dyn LowerBound],
+    max_cost: usize,
+) -> Option<Vec<Move>> {
+    let estimate = |c: &Cube| tables.iter().map(|t| t.estimate(c)).max().unwrap_or(0);
+    Solutions::new(cube, moves, metric, Box::new(goal), Box::new(estimate), max_cost).next()
+}
+
+// One node of the search: the cube, the cost and the move that led to it, and the next move to try
+struct Frame {
+    cube: Cube,
+    cost: usize,
+    last: Option<Move>,
+    next: usize,
+}
+
+/**
+Every solution of a stage, cheapest first, found by IDA* that keeps searching after a hit.
+- A solution ends at the first state where `goal` is true, it is never extended past it.
+- Moves that commute are only tried in one order and a layer never turns twice in a row (see
+  `is_redundant`), so two solutions never differ only by the order of commuting moves.
+- `estimate` must be a lower bound of the cost in `metric`.
+- With a deadline or a node limit the search ends early, `timed_out` then tells it apart from
+  having no more solutions.
+*/
+pub struct Solutions<'a> {
+    start: Cube,
+    moves: Vec<(Move, usize)>,
+    goal: Box<dyn Fn(&Cube) -> bool + 'a>,
+    estimate: Box<dyn Fn(&Cube) -> usize + 'a>,
+    max_cost: usize,
+    bound: usize, // Cost of the solutions of the current iteration
+    stack: Vec<Frame>,
+    started: bool,
+    nodes: u64, // Nodes entered so far, over all iterations
+    deadline: Option<Instant>,
+    max_nodes: Option<u64>,
+    timed_out: bool,
+}
+
+impl<'a> Solutions<'a> {
+    pub fn new(
+        cube: &Cube,
+        moves: &[Move],
+        metric: Metric,
+        goal: Box<dyn Fn(&Cube) -> bool + 'a>,
+        estimate: Box<dyn Fn(&Cube) -> usize + 'a>,
+        max_cost: usize,
+    ) -> Self {
+        let bound = estimate(cube);
+        Solutions {
+            start: *cube,
+            moves: moves.iter().map(|m| (*m, metric.cost(m))).collect(),
+            goal,
+            estimate,
+            max_cost,
+            bound,
+            stack: Vec::new(),
+            started: false,
+            nodes: 0,
+            deadline: None,
+            max_nodes: None,
+            timed_out: false,
+        }
+    }
+
+    // Stop searching at `deadline`
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    // Stop searching after `max_nodes` nodes, where there is no clock (WebAssembly)
+    pub fn with_node_limit(mut self, max_nodes: u64) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    // Enter a node, returns the path when it is a solution of the current cost
+    fn enter(&mut self, cube: Cube, cost: usize, last: Option<Move>) -> Option<Vec<Move>> {
+        self.nodes += 1;
+        if cost + (self.estimate)(&cube) > self.bound {
+            return None;
+        }
+        if (self.goal)(&cube) {
+            // Cheaper solutions were given by the previous iterations
+            return (cost == self.bound).then(|| self.stack.iter().filter_map(|f| f.last).chain(last).collect());
+        }
+        if cost < self.bound {
+            self.stack.push(Frame { cube, cost, last, next: 0 });
+        }
+        None
+    }
+}
+
+impl Iterator for Solutions<'_> {
+    type Item = Vec<Move>;
+
+    fn next(&mut self) -> Option<Vec<Move>> {
+        loop {
+            // The clock is only read now and then, it costs more than a node
+            if self.nodes.is_multiple_of(DEADLINE_CHECK_NODES) && self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                self.timed_out = true;
+            }
+            if self.max_nodes.is_some_and(|max_nodes| self.nodes >= max_nodes) {
+                self.timed_out = true;
+            }
+            if self.timed_out {
+                return None;
+            }
+            let Some(frame) = self.stack.last_mut() else {
+                // Next iteration, with a higher bound
+                if self.started {
+                    self.bound += 1;
+                }
+                self.started = true;
+                if self.bound > self.max_cost {
+                    return None;
+                }
+                if let Some(solution) = self.enter(self.start, 0, None) {
+                    return Some(solution);
+                }
+                continue;
+            };
+
+            let Some(&(m, move_cost)) = self.moves.get(frame.next) else {
+                self.stack.pop();
+                continue;
+            };
+            frame.next += 1;
+            if is_redundant(frame.last.as_ref(), &m) || frame.cost + move_cost > self.bound {
+                continue;
+            }
+            let mut next = frame.cube;
+            m.apply(&mut next);
+            let cost = frame.cost + move_cost;
+            if let Some(solution) = self.enter(next, cost, Some(m)) {
+                return Some(solution);
+            }
+        }
+    }
+}
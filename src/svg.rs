@@ -0,0 +1,199 @@
+/*!
+* SVG images of the cube, for documentation and algorithm sheets
+* - The unfolded net (same layout as the terminal net), an isometric view showing U, F and R, and
+*   a top view of the last layer with the top row of the side faces.
+* - Last layer diagrams can show PLL arrows: where each U layer piece goes.
+* - Colors come from a `ColorScheme`, masked stickers (see `Cube::masked`) are drawn gray.
+* - The SVG is written by hand, `Svg` only knows the few shapes used here.
+*/
+
+use crate::cube::Cube;
+use crate::render::ColorScheme;
+use std::fmt;
+
+const STICKER: f64 = 30.0; // Size of a sticker in the net and the last layer views
+const MARGIN: f64 = 5.0;
+const STROKE: &str = "#000000";
+
+// A minimal SVG document
+#[derive(Clone, Debug)]
+pub struct Svg {
+    pub width: f64,
+    pub height: f64,
+    elements: Vec<String>,
+    has_arrows: bool,
+}
+
+impl Svg {
+    pub fn new(width: f64, height: f64) -> Self {
+        Svg { width, height, elements: Vec::new(), has_arrows: false }
+    }
+
+    pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64, fill: &str) {
+        self.elements.push(format!(
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}" stroke="{}" stroke-width="1.5"/>"#,
+            x, y, width, height, fill, STROKE
+        ));
+    }
+
+    pub fn polygon(&mut self, points: &[(f64, f64)], fill: &str) {
+        let points: Vec<String> = points.iter().map(|(x, y)| format!("{:.1},{:.1}", x, y)).collect();
+        self.elements.push(format!(
+            r#"<polygon points="{}" fill="{}" stroke="{}" stroke-width="1.5" stroke-linejoin="round"/>"#,
+            points.join(" "), fill, STROKE
+        ));
+    }
+
+    // Line with an arrow head at the end, and at the start too when `both`
+    pub fn arrow(&mut self, from: (f64, f64), to: (f64, f64), both: bool) {
+        self.has_arrows = true;
+        let start = if both { r#" marker-start="url(#head)""# } else { "" };
+        self.elements.push(format!(
+            r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-width="2.5"{} marker-end="url(#head)"/>"#,
+            from.0, from.1, to.0, to.1, STROKE, start
+        ));
+    }
+}
+
+impl fmt::Display for Svg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.0}" height="{h:.0}" viewBox="0 0 {w:.1} {h:.1}">"#,
+            w = self.width, h = self.height
+        )?;
+        if self.has_arrows {
+            writeln!(
+                f,
+                r#"<defs><marker id="head" viewBox="0 0 10 10" refX="8" refY="5" markerWidth="5" markerHeight="5" orient="auto-start-reverse"><path d="M0,0 L10,5 L0,10 z" fill="{}"/></marker></defs>"#,
+                STROKE
+            )?;
+        }
+        for element in &self.elements {
+            writeln!(f, "{}", element)?;
+        }
+        writeln!(f, "</svg>")
+    }
+}
+
+fn fill(scheme: &ColorScheme, sticker: u8) -> String {
+    let [r, g, b] = scheme.rgb(sticker);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+// Faces of the net by (face, row, column) in faces, as in the terminal net
+const NET: [(usize, usize, usize); 6] = [(0, 0, 1), (3, 1, 0), (4, 1, 1), (2, 1, 2), (5, 1, 3), (1, 2, 1)];
+
+// The unfolded net: U on top, L F R B in the middle, D at the bottom
+pub fn net_svg(cube: &Cube, scheme: &ColorScheme) -> Svg {
+    let face_size = STICKER * Cube::EDGE_SIZE as f64 + MARGIN;
+    let mut svg = Svg::new(4.0 * face_size + MARGIN, 3.0 * face_size + MARGIN);
+    for (face, net_row, net_column) in NET {
+        for i in 0..Cube::FACE_SIZE {
+            let x = MARGIN + net_column as f64 * face_size + (i % Cube::EDGE_SIZE) as f64 * STICKER;
+            let y = MARGIN + net_row as f64 * face_size + (i / Cube::EDGE_SIZE) as f64 * STICKER;
+            svg.rect(x, y, STICKER, STICKER, &fill(scheme, cube.state[face * Cube::FACE_SIZE + i]));
+        }
+    }
+    svg
+}
+
+/**
+Isometric view of the U, F and R faces.
+- The cube fills [0, 3] on each axis: x to the right, y up and z to the front.
+*/
+pub fn isometric_svg(cube: &Cube, scheme: &ColorScheme) -> Svg {
+    let scale = STICKER;
+    let cos30 = 3f64.sqrt() / 2.0;
+    let width = 2.0 * 3.0 * cos30 * scale + 2.0 * MARGIN;
+    let project = |(x, y, z): (f64, f64, f64)| {
+        (MARGIN + (3.0 + x - z) * cos30 * scale, MARGIN + (3.0 + (x + z) / 2.0 - y) * scale)
+    };
+    let mut svg = Svg::new(width, 6.0 * scale + 2.0 * MARGIN);
+
+    // Corners of the sticker at (row, column) of each face, in 3D
+    type Corner = fn(f64, f64) -> (f64, f64, f64);
+    let faces: [(usize, Corner); 3] = [
+        (0, |r, c| (c, 3.0, r)),             // U: rows from the back, columns from the left
+        (4, |r, c| (c, 3.0 - r, 3.0)),       // F: rows from the top, columns from the left
+        (2, |r, c| (3.0, 3.0 - r, 3.0 - c)), // R: rows from the top, columns from the front
+    ];
+    for (face, corner) in faces {
+        for i in 0..Cube::FACE_SIZE {
+            let (r, c) = ((i / Cube::EDGE_SIZE) as f64, (i % Cube::EDGE_SIZE) as f64);
+            let points = [corner(r, c), corner(r, c + 1.0), corner(r + 1.0, c + 1.0), corner(r + 1.0, c)].map(project);
+            svg.polygon(&points, &fill(scheme, cube.state[face * Cube::FACE_SIZE + i]));
+        }
+    }
+    svg
+}
+
+/**
+Top view of the last layer: the U face with the top row of each side face around it.
+- With `arrows`, an arrow goes from each U layer piece to where it belongs, a double arrow for
+  2 pieces that swap. Arrows need a cube whose pieces can be identified (not masked).
+*/
+pub fn last_layer_svg(cube: &Cube, scheme: &ColorScheme, arrows: bool) -> Svg {
+    let side = STICKER / 3.0; // Thickness of the side stickers
+    let origin = MARGIN + side + MARGIN; // Top left of the U face
+    let size = 2.0 * origin + 3.0 * STICKER;
+    let mut svg = Svg::new(size, size);
+
+    for i in 0..Cube::FACE_SIZE {
+        let (x, y) = (origin + (i % 3) as f64 * STICKER, origin + (i / 3) as f64 * STICKER);
+        svg.rect(x, y, STICKER, STICKER, &fill(scheme, cube.state[i]));
+    }
+    let far = origin + 3.0 * STICKER + MARGIN; // Side stickers right of or below the U face
+    for k in 0..Cube::EDGE_SIZE {
+        let along = origin + k as f64 * STICKER;
+        let top_row = |face: usize, i: usize| fill(scheme, cube.state[face * Cube::FACE_SIZE + i]);
+        svg.rect(along, MARGIN, STICKER, side, &top_row(5, 2 - k)); // B stickers go from right to left
+        svg.rect(along, far, STICKER, side, &top_row(4, k));
+        svg.rect(MARGIN, along, side, STICKER, &top_row(3, k)); // L stickers go from back to front
+        svg.rect(far, along, side, STICKER, &top_row(2, 2 - k)); // R stickers go from front to back
+    }
+
+    if arrows {
+        let center = |sticker: usize| {
+            (origin + ((sticker % 3) as f64 + 0.5) * STICKER, origin + ((sticker / 3) as f64 + 0.5) * STICKER)
+        };
+        let moves = piece_moves(cube);
+        for &(from, to) in &moves {
+            if moves.contains(&(to, from)) {
+                if from < to {
+                    svg.arrow(shorten(center(from), center(to)), shorten(center(to), center(from)), true);
+                }
+            } else {
+                svg.arrow(shorten(center(from), center(to)), shorten(center(to), center(from)), false);
+            }
+        }
+    }
+    svg
+}
+
+// Move the start of a segment a bit toward its end, so arrows don't cover sticker centers
+fn shorten(from: (f64, f64), to: (f64, f64)) -> (f64, f64) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    let cut = (STICKER * 0.25).min(length / 3.0);
+    (from.0 + dx / length * cut, from.1 + dy / length * cut)
+}
+
+// U face stickers (0..9) of each U layer piece and of where it belongs, for pieces not at home
+fn piece_moves(cube: &Cube) -> Vec<(usize, usize)> {
+    let Some(labelled) = cube.labelled() else { return Vec::new() };
+    let corners = Cube::CORNER_FACELETS[..4].iter().map(|c| c.as_slice());
+    let edges = Cube::EDGE_FACELETS[..4].iter().map(|e| e.as_slice());
+    let pieces: Vec<&[usize]> = corners.chain(edges).collect();
+
+    let mut moves = Vec::new();
+    for piece in &pieces {
+        let label = labelled.state[piece[0]] as usize;
+        if let Some(home) = pieces.iter().find(|home| home.contains(&label)) {
+            if home[0] != piece[0] {
+                moves.push((piece[0], home[0]));
+            }
+        }
+    }
+    moves
+}
@@ -0,0 +1,204 @@
+/*!
+* Algorithm trainer
+* - A drill is a random case of one set, set up from the solved cube with a random AUF before and
+*   after it. Optional noise keeps the case but changes the rest of the last layer: a random PLL
+*   before an OLL case, a random EPLL before a CMLL or COLL case, a random OLL before an F2L case.
+* - Stats per case (attempts, right recognitions, times) are kept in a text file, one case per line.
+*/
+
+use crate::cases::{aufs, case_by_id, cases_of, find_case, Case, CaseSet};
+use crate::cube::Cube;
+use crate::notation::{apply_moves, face_moves, simplify_moves, Move};
+use crate::stage::is_redundant;
+use std::collections::BTreeMap;
+use std::fmt;
+#[cfg(feature = "native")]
+use std::fs;
+use std::time::Duration;
+#[cfg(feature = "native")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// PLLs that only move edges, used as noise for the corner sets
+const EPLL: [&str; 4] = ["Ua", "Ub", "H", "Z"];
+
+// xorshift64* generator, good enough to pick cases
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.max(1)) // The state must never be 0
+    }
+
+    #[cfg(feature = "native")]
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(1);
+        Rng::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // Uniform in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // Random face moves, without two moves of the same layer or commuting moves in both orders
+    pub fn scramble(&mut self, length: usize) -> Vec<Move> {
+        let moves = face_moves();
+        let mut scramble: Vec<Move> = Vec::new();
+        while scramble.len() < length {
+            let m = moves[self.below(moves.len())];
+            if !is_redundant(scramble.last(), &m) {
+                scramble.push(m);
+            }
+        }
+        scramble
+    }
+
+    fn auf(&mut self) -> Option<Move> {
+        aufs()[self.below(4)]
+    }
+}
+
+pub struct Drill {
+    pub case: &'static Case,
+    pub scramble: Vec<Move>,
+    pub cube: Cube,
+}
+
+// Random case of the set with its setup scramble
+pub fn new_drill(set: CaseSet, noise: bool, rng: &mut Rng) -> Drill {
+    let cases: Vec<&'static Case> = cases_of(set).collect();
+    let case = cases[rng.below(cases.len())];
+
+    let noise_cases: Vec<&'static Case> = match set {
+        CaseSet::Oll if noise => cases_of(CaseSet::Pll).collect(),
+        CaseSet::Cmll | CaseSet::Coll if noise => EPLL.iter().filter_map(|name| find_case(CaseSet::Pll, name)).collect(),
+        CaseSet::F2l if noise => cases_of(CaseSet::Oll).collect(),
+        _ => Vec::new(),
+    };
+    let mut moves: Vec<Move> = Vec::new();
+    if !noise_cases.is_empty() {
+        let noise_case = noise_cases[rng.below(noise_cases.len())];
+        moves.extend(rng.auf());
+        moves.extend(&noise_case.setup);
+    }
+    moves.extend(rng.auf());
+    moves.extend(&case.setup);
+    moves.extend(rng.auf());
+
+    let scramble = simplify_moves(&moves);
+    let mut cube = Cube::new(None).expect("Error initializing cube");
+    apply_moves(&mut cube, &scramble);
+    Drill { case, scramble, cube }
+}
+
+// True when the answer names the case, with or without its set: "T", "pll t", "Pi Left Bar"
+pub fn is_right_answer(case: &Case, answer: &str) -> bool {
+    let answer = answer.trim();
+    find_case(case.set, answer).is_some_and(|named| named.name == case.name)
+        || case_by_id(answer).is_some_and(|named| named.set == case.set && named.name == case.name)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CaseStats {
+    pub attempts: u32,
+    pub recognized: u32, // Attempts where the case was named right
+    pub total: Duration,
+    pub best: Option<Duration>,
+}
+
+impl CaseStats {
+    pub fn mean(&self) -> Option<Duration> {
+        (self.attempts > 0).then(|| self.total / self.attempts)
+    }
+}
+
+/**
+Stats of every case trained, by case ID ("PLL T").
+- Saved as tab separated lines: case, attempts, recognized, total time and best time in
+  milliseconds ("-" when there is none). Lines starting with # are comments.
+*/
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub cases: BTreeMap<String, CaseStats>,
+}
+
+impl Stats {
+    // Stats saved in a file, empty when the file doesn't exist yet
+    #[cfg(feature = "native")]
+    pub fn load(path: &str) -> Result<Stats, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Stats::default()),
+            Err(e) => return Err(format!("Can't read {}: {}", path, e)),
+        };
+
+        let mut stats = Stats::default();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || format!("Invalid line {} in {}", i + 1, path);
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [case, attempts, recognized, total, best] = fields[..] else { return Err(invalid()) };
+            let number = |field: &str| field.parse::<u64>().map_err(|_| invalid());
+            let case_stats = CaseStats {
+                attempts: number(attempts)? as u32,
+                recognized: number(recognized)? as u32,
+                total: Duration::from_millis(number(total)?),
+                best: match best {
+                    "-" => None,
+                    best => Some(Duration::from_millis(number(best)?)),
+                },
+            };
+            stats.cases.insert(case.to_string(), case_stats);
+        }
+        Ok(stats)
+    }
+
+    #[cfg(feature = "native")]
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut text = String::from("# case\tattempts\trecognized\ttotal_ms\tbest_ms\n");
+        for (case, stats) in &self.cases {
+            let best = stats.best.map_or("-".to_string(), |best| best.as_millis().to_string());
+            text += &format!("{}\t{}\t{}\t{}\t{}\n", case, stats.attempts, stats.recognized, stats.total.as_millis(), best);
+        }
+        fs::write(path, text).map_err(|e| format!("Can't write {}: {}", path, e))
+    }
+
+    pub fn record(&mut self, case: &Case, time: Duration, recognized: bool) {
+        let stats = self.cases.entry(case.to_string()).or_default();
+        stats.attempts += 1;
+        stats.recognized += recognized as u32;
+        stats.total += time;
+        stats.best = Some(stats.best.map_or(time, |best| best.min(time)));
+    }
+
+    // Only the cases of one set
+    pub fn of_set(&self, set: CaseSet) -> Stats {
+        let prefix = format!("{} ", set);
+        let cases = self.cases.iter()
+            .filter(|(case, _)| case.starts_with(&prefix))
+            .map(|(case, stats)| (case.clone(), stats.clone()))
+            .collect();
+        Stats { cases }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds = |time: Option<Duration>| time.map_or("-".to_string(), |t| format!("{:.2}", t.as_secs_f64()));
+        writeln!(f, "{:<12} {:>8} {:>11} {:>8} {:>8}", "Case", "Attempts", "Recognized", "Mean", "Best")?;
+        for (case, stats) in &self.cases {
+            let recognized = format!("{}%", 100 * stats.recognized / stats.attempts.max(1));
+            writeln!(f, "{:<12} {:>8} {:>11} {:>8} {:>8}", case, stats.attempts, recognized, seconds(stats.mean()), seconds(stats.best))?;
+        }
+        Ok(())
+    }
+}
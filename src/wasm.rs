@@ -0,0 +1,242 @@
+/*!
+* String API for WebAssembly (see wasm/rubik.js)
+* - Build: `cargo build --release --target wasm32-unknown-unknown --no-default-features`, then
+*   load target/wasm32-unknown-unknown/release/rubik.wasm with wasm/rubik.js.
+* - Without the `native` feature the library has no threads, files, sockets or clock, so
+*   searches are limited by a node count instead of a time limit.
+* - Every call returns a JSON object as text, `{"error": "..."}` when it fails.
+* - The optimal search uses small pattern databases (3 pieces per table by default, built in
+*   well under a second). `configure` trades their memory for search speed.
+* - Roux and ZZ are there too, their tables are larger and slower to build on first use.
+*/
+
+use crate::cube::Cube;
+use crate::ida_star::{metric_tables, solutions_with_tables, TableSize};
+use crate::json::Json;
+use crate::notation::{apply_moves, format_moves, parse_moves, Metric};
+use crate::roux::solve_roux;
+use crate::stage::PieceTable;
+use crate::train::Rng;
+use crate::zz::{solve_zz, ZzStart};
+use std::sync::{Arc, Mutex};
+
+const OPTIMAL_MAX_COST: usize = 20;
+const DEFAULT_MAX_NODES: u64 = 20_000_000;
+const MAX_SCRAMBLE_LENGTH: usize = 100;
+const MAX_TABLE_PIECES: usize = 4;
+
+// Size of the tables of the next optimal search, and the last tables built with their metric
+struct Tables {
+    size: TableSize,
+    built: Option<(&'static str, TableSize, Arc<Vec<PieceTable>>)>,
+}
+
+static TABLES: Mutex<Tables> = Mutex::new(Tables { size: TableSize { pieces: 3, max_cost: 14 }, built: None });
+
+fn object(entries: Vec<(&str, Json)>) -> Json {
+    Json::Object(entries.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
+
+// An empty string is the solved cube
+fn read_cube(facelets: &str) -> Result<Cube, String> {
+    match facelets.trim() {
+        "" => Cube::new(None),
+        facelets => Cube::from_facelets(facelets),
+    }
+}
+
+fn metric_named(name: &str) -> Result<(&'static str, Metric), String> {
+    match name {
+        "" | "htm" => Ok(("htm", Metric::Htm)),
+        "qtm" => Ok(("qtm", Metric::Qtm)),
+        "stm" => Ok(("stm", Metric::Stm)),
+        _ => Err(format!("Unknown metric: {} (htm, qtm or stm)", name)),
+    }
+}
+
+/**
+Size of the pattern databases of the optimal search: pieces per table (1 to 4) and the largest
+stored cost. Tables already built are dropped when the size changes.
+*/
+pub fn configure(pieces: usize, max_cost: usize) -> Result<Json, String> {
+    if !(1..=MAX_TABLE_PIECES).contains(&pieces) {
+        return Err(format!("pieces must be 1 to {}", MAX_TABLE_PIECES));
+    }
+    if !(1..=OPTIMAL_MAX_COST).contains(&max_cost) {
+        return Err(format!("max_cost must be 1 to {}", OPTIMAL_MAX_COST));
+    }
+    let mut tables = TABLES.lock().unwrap_or_else(|e| e.into_inner());
+    tables.size = TableSize { pieces, max_cost };
+    if tables.built.as_ref().is_some_and(|(_, size, _)| *size != tables.size) {
+        tables.built = None;
+    }
+    Ok(object(vec![("pieces", Json::from(pieces as f64)), ("max_cost", Json::from(max_cost as f64))]))
+}
+
+// Random scramble of `length` face moves, the seed comes from the caller (e.g. Math.random)
+pub fn scramble(length: usize, seed: u64) -> Result<Json, String> {
+    if length > MAX_SCRAMBLE_LENGTH {
+        return Err(format!("length must be up to {}", MAX_SCRAMBLE_LENGTH));
+    }
+    let scramble = Rng::new(seed).scramble(length);
+    let mut cube = Cube::new(None)?;
+    apply_moves(&mut cube, &scramble);
+    Ok(object(vec![
+        ("scramble", Json::from(format_moves(&scramble))),
+        ("facelets", Json::from(cube.facelets())),
+    ]))
+}
+
+// Whether the stickers are a real cube, and whether moves can solve it
+pub fn validate(facelets: &str) -> Result<Json, String> {
+    Ok(match Cube::from_facelets(facelets.trim()) {
+        Ok(cube) => object(vec![
+            ("valid", Json::from(true)),
+            ("solvable", Json::from(cube.is_solvable())),
+            ("solved", Json::from(cube.is_solved())),
+        ]),
+        Err(e) => object(vec![("valid", Json::from(false)), ("error", Json::from(e))]),
+    })
+}
+
+// The cube after the moves
+pub fn apply(facelets: &str, moves: &str) -> Result<Json, String> {
+    let mut cube = read_cube(facelets)?;
+    apply_moves(&mut cube, &parse_moves(moves)?);
+    Ok(object(vec![("facelets", Json::from(cube.facelets())), ("solved", Json::from(cube.is_solved()))]))
+}
+
+/**
+Solve the cube with "optimal" (default), "roux" or "zz".
+- The optimal search stops after `max_nodes` nodes (0 for the default), `metric` is htm, qtm
+  or stm.
+*/
+pub fn solve(facelets: &str, method: &str, metric: &str, max_nodes: u64) -> Result<Json, String> {
+    let cube = read_cube(facelets)?;
+    if !cube.is_solvable() {
+        return Err("The cube can't be solved".to_string());
+    }
+    let (moves, nodes) = match method {
+        "" | "optimal" => {
+            let (name, metric) = metric_named(metric)?;
+            let tables = {
+                let mut tables = TABLES.lock().unwrap_or_else(|e| e.into_inner());
+                let size = tables.size;
+                match &tables.built {
+                    Some((built, built_size, built_tables)) if *built == name && *built_size == size => Arc::clone(built_tables),
+                    _ => {
+                        let built = Arc::new(metric_tables(metric, size));
+                        tables.built = Some((name, size, Arc::clone(&built)));
+                        built
+                    }
+                }
+            };
+            let max_nodes = if max_nodes == 0 { DEFAULT_MAX_NODES } else { max_nodes };
+            let mut search = solutions_with_tables(&cube, metric, tables, OPTIMAL_MAX_COST)
+                .ok_or("The pieces can't be identified")?
+                .with_node_limit(max_nodes);
+            match search.next() {
+                Some(moves) => (moves, Some(search.nodes())),
+                None if search.timed_out() => return Err(format!("No solution within {} nodes", max_nodes)),
+                None => return Err(format!("No solution within {} {}", OPTIMAL_MAX_COST, metric)),
+            }
+        }
+        "roux" => (solve_roux(&cube)?.moves(), None),
+        "zz" => (solve_zz(&cube, ZzStart::EoLine)?.moves(), None),
+        _ => return Err(format!("Unknown method: {} (optimal, roux or zz)", method)),
+    };
+
+    let mut entries = vec![
+        ("solution", Json::from(format_moves(&moves))),
+        ("length", Json::from(moves.len() as f64)),
+    ];
+    entries.extend(nodes.map(|nodes| ("nodes", Json::from(nodes as f64))));
+    Ok(object(entries))
+}
+
+/**
+The raw exports for JavaScript: strings go in as (pointer, length) of UTF-8 bytes in a buffer
+from `rubik_wasm_alloc`, which the call frees. The result is a NUL terminated JSON string to free
+with `rubik_wasm_free_string`.
+- The `rubik_wasm_` prefix keeps them apart from the C API (`ffi`) when both are built.
+*/
+#[cfg(target_arch = "wasm32")]
+mod exports {
+    use super::*;
+    use std::ffi::{c_char, CString};
+
+    #[no_mangle]
+    pub extern "C" fn rubik_wasm_alloc(length: usize) -> *mut u8 {
+        Box::into_raw(vec![0u8; length].into_boxed_slice()) as *mut u8
+    }
+
+    // Take back a buffer from `rubik_wasm_alloc`
+    unsafe fn take_string(pointer: *mut u8, length: usize) -> String {
+        let bytes = Box::from_raw(std::ptr::slice_from_raw_parts_mut(pointer, length));
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    fn result(result: Result<Json, String>) -> *mut c_char {
+        let json = result.unwrap_or_else(|e| object(vec![("error", Json::from(e))]));
+        CString::new(json.to_string()).expect("JSON text has no NUL").into_raw()
+    }
+
+    /**
+    # Safety
+    `text` must come from a result of this module, and not be used afterwards.
+    */
+    #[no_mangle]
+    pub unsafe extern "C" fn rubik_wasm_free_string(text: *mut c_char) {
+        if !text.is_null() {
+            drop(CString::from_raw(text));
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn rubik_wasm_configure(pieces: u32, max_cost: u32) -> *mut c_char {
+        result(configure(pieces as usize, max_cost as usize))
+    }
+
+    #[no_mangle]
+    pub extern "C" fn rubik_wasm_scramble(length: u32, seed: u32) -> *mut c_char {
+        result(scramble(length as usize, seed as u64))
+    }
+
+    /**
+    # Safety
+    The string must be a buffer from `rubik_wasm_alloc`.
+    */
+    #[no_mangle]
+    pub unsafe extern "C" fn rubik_wasm_validate(facelets: *mut u8, facelets_length: usize) -> *mut c_char {
+        result(validate(&take_string(facelets, facelets_length)))
+    }
+
+    /**
+    # Safety
+    The strings must be buffers from `rubik_wasm_alloc`.
+    */
+    #[no_mangle]
+    pub unsafe extern "C" fn rubik_wasm_apply(facelets: *mut u8, facelets_length: usize, moves: *mut u8, moves_length: usize) -> *mut c_char {
+        result(apply(&take_string(facelets, facelets_length), &take_string(moves, moves_length)))
+    }
+
+    /**
+    # Safety
+    The strings must be buffers from `rubik_wasm_alloc`.
+    */
+    #[no_mangle]
+    pub unsafe extern "C" fn rubik_wasm_solve(
+        facelets: *mut u8,
+        facelets_length: usize,
+        method: *mut u8,
+        method_length: usize,
+        metric: *mut u8,
+        metric_length: usize,
+        max_nodes: f64, // JavaScript numbers, u64 would need a BigInt
+    ) -> *mut c_char {
+        let facelets = take_string(facelets, facelets_length);
+        let method = take_string(method, method_length);
+        let metric = take_string(metric, metric_length);
+        result(solve(&facelets, &method, &metric, max_nodes.max(0.0) as u64))
+    }
+}
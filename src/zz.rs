@@ -0,0 +1,125 @@
+/*!
+* ZZ method solver
+* 1. EOLine (or EOCross): orient all edges relative to the F/B axis and solve DF, DB (and DL, DR)
+* 2. ZZF2L: left block then right block, only with <R, U, L> so the edges stay oriented
+* 3. OCLL: orient the last layer corners (the edges are already oriented), the OLL case from
+*    the database (see `cases`) with its AUF
+* 4. PLL: permute the last layer, the PLL case from the database with its AUFs
+*/
+
+use crate::cases::{solve_case, CaseSet};
+use crate::cube::{Axis, Cube, Face};
+use crate::notation::{face_moves, layer_moves, Layer, Move};
+use crate::stage::*;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZzStart {
+    EoLine,  // EO + DF, DB
+    EoCross, // EO + DF, DB, DL, DR
+}
+
+// Pieces of each stage (indices into Cube::CORNER_FACELETS / Cube::EDGE_FACELETS)
+const LINE_EDGES: [usize; 2] = [5, 7]; // DF, DB
+const CROSS_EDGES: [usize; 4] = [5, 7, 6, 4]; // DF, DB, DL, DR
+const LEFT_CORNERS: [usize; 2] = [5, 6]; // DLF, DBL
+const LEFT_EDGES: [usize; 3] = [6, 9, 10]; // DL, FL, BL
+const RIGHT_CORNERS: [usize; 2] = [4, 7]; // DFR, DRB
+const RIGHT_EDGES: [usize; 3] = [4, 8, 11]; // DR, FR, BR
+
+const EO_MAX_DEPTH: usize = 14;
+const F2L_MAX_DEPTH: usize = 24;
+
+struct ZzTables {
+    eo: CoordTable,
+    line: PieceTable,
+    cross: PieceTable,
+    left: PieceTable,
+    right: PieceTable,
+}
+
+static TABLES: OnceLock<ZzTables> = OnceLock::new();
+
+fn tables() -> &'static ZzTables {
+    TABLES.get_or_init(|| {
+        let all = face_moves();
+        ZzTables {
+            eo: CoordTable::new(eo_key, &all, EO_MAX_DEPTH),
+            line: PieceTable::new(&reference_stickers(&[], &LINE_EDGES), &all, EO_MAX_DEPTH),
+            cross: PieceTable::new(&reference_stickers(&[], &CROSS_EDGES), &all, EO_MAX_DEPTH),
+            left: PieceTable::new(&reference_stickers(&LEFT_CORNERS, &LEFT_EDGES), &rul_moves(), F2L_MAX_DEPTH),
+            right: PieceTable::new(&reference_stickers(&RIGHT_CORNERS, &RIGHT_EDGES), &ru_moves(), F2L_MAX_DEPTH),
+        }
+    })
+}
+
+// Build the tables now rather than in the first solve
+pub fn preload_tables() {
+    tables();
+}
+
+fn rul_moves() -> Vec<Move> {
+    layer_moves(&[Layer::Face(Face::R), Layer::Face(Face::U), Layer::Face(Face::L)])
+}
+
+fn ru_moves() -> Vec<Move> {
+    layer_moves(&[Layer::Face(Face::R), Layer::Face(Face::U)])
+}
+
+// Flipped edges relative to F/B as a 12 bit number
+fn eo_key(cube: &Cube) -> u64 {
+    Cube::labelled_flipped_edges(cube, Axis::FB).iter()
+        .fold(0, |acc, &flipped| (acc << 1) | flipped as u64)
+}
+
+/**
+Solve the cube with the ZZ method.
+- Each stage is reported separately with its own moves.
+- Returns an error when the cube can't be labelled or a stage can't be solved.
+*/
+pub fn solve_zz(cube: &Cube, start: ZzStart) -> Result<MethodSolution, String> {
+    solve_zz_until(cube, start, None)
+}
+
+// Same as `solve_zz`, the EO and F2L searches give up at `deadline` (the stage is then not found)
+pub fn solve_zz_until(cube: &Cube, start: ZzStart, deadline: Option<Instant>) -> Result<MethodSolution, String> {
+    let tables = tables();
+    let mut state = cube.labelled().ok_or("Invalid cube: pieces can't be identified")?;
+    let mut solution = MethodSolution { stages: Vec::new() };
+
+    let (name, edges, edge_table): (_, &[usize], _) = match start {
+        ZzStart::EoLine => ("EOLine", &LINE_EDGES, &tables.line),
+        ZzStart::EoCross => ("EOCross", &CROSS_EDGES, &tables.cross),
+    };
+    let edge_goal = piece_stickers(&[], edges);
+    let eo = solve_stage_until(
+        &state,
+        &face_moves(),
+        &|c| eo_key(c) == 0 && is_home(c, &edge_goal),
+        &[&tables.eo, edge_table],
+        EO_MAX_DEPTH,
+        deadline,
+    )
+    .ok_or("EO stage not found")?;
+    solution.push_stage(&mut state, name, eo);
+
+    let left_goal = piece_stickers(&LEFT_CORNERS, &LEFT_EDGES);
+    let left = solve_stage_until(&state, &rul_moves(), &|c| is_home(c, &left_goal), &[&tables.left], F2L_MAX_DEPTH, deadline)
+        .ok_or("Left F2L block not found")?;
+    solution.push_stage(&mut state, "F2L-L", left);
+
+    let right_goal = piece_stickers(&RIGHT_CORNERS, &RIGHT_EDGES);
+    let right = solve_stage_until(&state, &ru_moves(), &|c| is_home(c, &right_goal), &[&tables.right], F2L_MAX_DEPTH, deadline)
+        .ok_or("Right F2L block not found")?;
+    solution.push_stage(&mut state, "F2L-R", right);
+
+    // Corners are oriented when their U sticker is on the U face
+    let ocll = solve_case(&state, CaseSet::Oll, &|c| [0, 2, 6, 8].iter().all(|&i| (c.state[i] as usize) < Cube::FACE_SIZE))?;
+    solution.push_stage(&mut state, "OCLL", ocll);
+
+    let pll = solve_case(&state, CaseSet::Pll, &|c| *c == identity())?;
+    solution.push_stage(&mut state, "PLL", pll);
+
+    Ok(solution)
+}
@@ -0,0 +1,118 @@
+/*!
+* The HTTP service over a real socket
+* - One server on a free port of 127.0.0.1 is shared by all the tests.
+*/
+
+use rubik::cube::Cube;
+use rubik::json::Json;
+use rubik::server::{serve_listener, ServerConfig};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::OnceLock;
+use std::thread;
+
+const SCRAMBLE: &str = "D2 F' U2 R2 B' L2 F R' D' L U2 B D2 R F2 U' L2 B R' D";
+
+fn address() -> SocketAddr {
+    static ADDRESS: OnceLock<SocketAddr> = OnceLock::new();
+    *ADDRESS.get_or_init(|| {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Can't bind a free port");
+        let address = listener.local_addr().expect("No local address");
+        let config = ServerConfig { workers: 2, ..ServerConfig::default() };
+        thread::spawn(move || serve_listener(listener, config));
+        address
+    })
+}
+
+// Status and body of the response
+fn request(method: &str, target: &str, body: &str) -> (u16, Json) {
+    let mut stream = TcpStream::connect(address()).expect("Can't connect to the server");
+    write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}", method, target, body.len(), body)
+        .expect("Can't send the request");
+    let mut response = String::new();
+    stream.read_to_string(&mut response).expect("Can't read the response");
+    let (head, body) = response.split_once("\r\n\r\n").expect("No end of headers");
+    let status = head.split_whitespace().nth(1).and_then(|status| status.parse().ok()).expect("No status");
+    (status, Json::parse(body).expect("The body isn't JSON"))
+}
+
+fn field<'a>(body: &'a Json, key: &str) -> &'a Json {
+    body.get(key).unwrap_or_else(|| panic!("No {} in {}", key, body))
+}
+
+fn solved_facelets() -> String {
+    Cube::new(None).unwrap().facelets()
+}
+
+#[test]
+fn scramble() {
+    let (status, body) = request("GET", "/scramble?length=15", "");
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(field(&body, "length").as_f64(), Some(15.0));
+    let facelets = field(&body, "facelets").as_str().unwrap();
+    assert!(Cube::from_facelets(facelets).unwrap().is_solvable());
+
+    let (status, _) = request("GET", "/scramble?length=1000", "");
+    assert_eq!(status, 400);
+}
+
+#[test]
+fn apply() {
+    let (status, body) = request("POST", "/apply", r#"{"moves": "R U"}"#);
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(field(&body, "solved").as_bool(), Some(false));
+
+    let facelets = field(&body, "facelets").as_str().unwrap();
+    let request_body = format!(r#"{{"facelets": "{}", "moves": "U' R'"}}"#, facelets);
+    let (status, body) = request("POST", "/apply", &request_body);
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(field(&body, "solved").as_bool(), Some(true));
+    assert_eq!(field(&body, "facelets").as_str(), Some(solved_facelets().as_str()));
+
+    let (status, _) = request("POST", "/apply", r#"{"moves": "R Q"}"#);
+    assert_eq!(status, 400);
+}
+
+#[test]
+fn validate() {
+    let (status, body) = request("POST", "/validate", &format!(r#"{{"facelets": "{}"}}"#, solved_facelets()));
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(field(&body, "valid").as_bool(), Some(true));
+    assert_eq!(field(&body, "solvable").as_bool(), Some(true));
+
+    let (status, body) = request("POST", "/validate", r#"{"facelets": "UUU"}"#);
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(field(&body, "valid").as_bool(), Some(false));
+}
+
+#[test]
+fn solve() {
+    let (status, body) = request("POST", "/solve", &format!(r#"{{"scramble": "{}", "method": "roux"}}"#, SCRAMBLE));
+    assert_eq!(status, 200, "{}", body);
+    let solution = field(&body, "solution").as_str().unwrap();
+    let request_body = format!(r#"{{"moves": "{} {}"}}"#, SCRAMBLE, solution);
+    let (_, body) = request("POST", "/apply", &request_body);
+    assert_eq!(field(&body, "solved").as_bool(), Some(true));
+
+    let (status, body) = request("POST", "/solve", r#"{"scramble": "R U R' U'", "method": "optimal"}"#);
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(field(&body, "length").as_f64(), Some(4.0));
+}
+
+#[test]
+fn solve_time_limit() {
+    let request_body = format!(r#"{{"scramble": "{}", "method": "optimal", "time_limit_ms": 1}}"#, SCRAMBLE);
+    let (status, body) = request("POST", "/solve", &request_body);
+    assert_eq!(status, 504, "{}", body);
+    assert_eq!(field(field(&body, "stats"), "timed_out").as_bool(), Some(true));
+}
+
+#[test]
+fn deep_nesting() {
+    let (status, body) = request("POST", "/solve", &"[".repeat(60_000));
+    assert_eq!(status, 400, "{}", body);
+
+    // The server is still up
+    let (status, _) = request("GET", "/scramble", "");
+    assert_eq!(status, 200);
+}
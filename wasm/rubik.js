@@ -0,0 +1,67 @@
+// JavaScript wrapper of the WebAssembly build (see src/wasm.rs).
+//
+//   const rubik = await Rubik.load(fetch("rubik.wasm"));
+//   rubik.configure(3, 14);                   // pattern database size, before the first solve
+//   const { scramble, facelets } = rubik.scramble(20);
+//   rubik.validate(facelets);                 // { valid, solvable, solved }
+//   rubik.apply(facelets, "R U R' U'");       // { facelets, solved }
+//   rubik.solve(facelets, { method: "optimal", metric: "htm", maxNodes: 1e7 });
+//                                             // { solution, length, nodes }
+//
+// Facelets are 54 letters (U, D, R, L, F, B faces), "" is the solved cube. Errors are thrown.
+
+export class Rubik {
+  constructor(instance) {
+    this.exports = instance.exports;
+    this.encoder = new TextEncoder();
+    this.decoder = new TextDecoder();
+  }
+
+  // From a Response, a promise of one, or the bytes of rubik.wasm
+  static async load(source) {
+    const module = await Promise.resolve(source);
+    const { instance } = module instanceof Response
+      ? await WebAssembly.instantiateStreaming(module, {})
+      : await WebAssembly.instantiate(module, {});
+    return new Rubik(instance);
+  }
+
+  // A string as (pointer, length) in a buffer the call takes over
+  string(text) {
+    const bytes = this.encoder.encode(text);
+    const pointer = this.exports.rubik_wasm_alloc(bytes.length);
+    new Uint8Array(this.exports.memory.buffer, pointer, bytes.length).set(bytes);
+    return [pointer, bytes.length];
+  }
+
+  result(pointer) {
+    const memory = new Uint8Array(this.exports.memory.buffer);
+    let end = pointer;
+    while (memory[end] !== 0) end++;
+    const json = JSON.parse(this.decoder.decode(memory.subarray(pointer, end)));
+    this.exports.rubik_wasm_free_string(pointer);
+    if (json.error !== undefined && json.valid === undefined) throw new Error(json.error);
+    return json;
+  }
+
+  configure(pieces, maxCost) {
+    return this.result(this.exports.rubik_wasm_configure(pieces, maxCost));
+  }
+
+  scramble(length = 20, seed = Math.floor(Math.random() * 2 ** 32)) {
+    return this.result(this.exports.rubik_wasm_scramble(length, seed));
+  }
+
+  validate(facelets) {
+    return this.result(this.exports.rubik_wasm_validate(...this.string(facelets)));
+  }
+
+  apply(facelets, moves) {
+    return this.result(this.exports.rubik_wasm_apply(...this.string(facelets), ...this.string(moves)));
+  }
+
+  solve(facelets, { method = "optimal", metric = "htm", maxNodes = 0 } = {}) {
+    return this.result(this.exports.rubik_wasm_solve(
+      ...this.string(facelets), ...this.string(method), ...this.string(metric), maxNodes));
+  }
+}